@@ -0,0 +1,199 @@
+//! Acquisition of connections in code generic over their source.
+//!
+//! [`Acquire`] hands out traced connections and transactions. The [`sqlx::Acquire`]
+//! implementations let the traced types be used in code written for SQLx, but the trait hands
+//! out the driver connection and a SQLx [`sqlx::Transaction`], so only the acquisition and the
+//! `BEGIN` statement are traced there. Queries run on the acquired connection go straight to
+//! the driver.
+
+use futures::future::BoxFuture;
+use tracing::Instrument;
+
+/// Acquires a traced connection or begins a traced transaction, from a [`Pool`](crate::Pool),
+/// a [`PoolConnection`](crate::PoolConnection) or a [`Transaction`](crate::Transaction).
+///
+/// The counterpart of [`sqlx::Acquire`], for code generic over the source of the connection,
+/// whose queries stay instrumented:
+///
+/// ```rust,ignore
+/// async fn insert_user<'c, A>(db: A) -> sqlx::Result<()>
+/// where
+///     A: sqlx_tracing::Acquire<'c, Database = sqlx::Postgres>,
+/// {
+///     let mut conn = db.acquire().await?;
+///     sqlx::query("insert into users default values").execute(&mut *conn).await?;
+///     Ok(())
+/// }
+/// ```
+pub trait Acquire<'c> {
+    type Database: crate::prelude::Database;
+
+    /// Traced connection, dereferencing to a [`Traced`](crate::Traced) executor.
+    type Connection: std::ops::DerefMut<Target = crate::Traced<Self::Inner>> + Send;
+
+    /// Connection wrapped by the traced executor.
+    type Inner: std::ops::DerefMut<Target = <Self::Database as sqlx::Database>::Connection> + Send;
+
+    /// Acquires a connection, or reborrows the one held.
+    fn acquire(self) -> BoxFuture<'c, Result<Self::Connection, sqlx::Error>>;
+
+    /// Begins a transaction, or a savepoint when already in a transaction.
+    fn begin(self) -> BoxFuture<'c, Result<crate::Transaction<'c, Self::Database>, sqlx::Error>>;
+}
+
+impl<'c, DB> Acquire<'c> for &'c crate::Pool<DB>
+where
+    DB: crate::prelude::Database,
+{
+    type Database = DB;
+
+    type Connection = crate::PoolConnection<DB>;
+
+    type Inner = sqlx::pool::PoolConnection<DB>;
+
+    fn acquire(self) -> BoxFuture<'c, Result<Self::Connection, sqlx::Error>> {
+        Box::pin(crate::Pool::acquire(self))
+    }
+
+    #[track_caller]
+    fn begin(self) -> BoxFuture<'c, Result<crate::Transaction<'c, DB>, sqlx::Error>> {
+        let fut = crate::Pool::begin(self);
+        Box::pin(async move {
+            // shortens the lifetime of the transaction owning its connection
+            let tx: crate::Transaction<'c, DB> = fut.await?;
+            Ok(tx)
+        })
+    }
+}
+
+impl<'c, DB> Acquire<'c> for &'c mut crate::PoolConnection<DB>
+where
+    DB: crate::prelude::Database,
+{
+    type Database = DB;
+
+    type Connection = &'c mut crate::Traced<sqlx::pool::PoolConnection<DB>>;
+
+    type Inner = sqlx::pool::PoolConnection<DB>;
+
+    fn acquire(self) -> BoxFuture<'c, Result<Self::Connection, sqlx::Error>> {
+        Box::pin(futures::future::ok(&mut self.inner))
+    }
+
+    #[track_caller]
+    fn begin(self) -> BoxFuture<'c, Result<crate::Transaction<'c, DB>, sqlx::Error>> {
+        Box::pin(crate::PoolConnection::begin(self))
+    }
+}
+
+impl<'c, 't, DB> Acquire<'t> for &'t mut crate::Transaction<'c, DB>
+where
+    DB: crate::prelude::Database,
+    for<'a> &'a mut DB::Connection: sqlx::Executor<'a, Database = DB>,
+{
+    type Database = DB;
+
+    type Connection = &'t mut crate::Traced<sqlx::Transaction<'c, DB>>;
+
+    type Inner = sqlx::Transaction<'c, DB>;
+
+    fn acquire(self) -> BoxFuture<'t, Result<Self::Connection, sqlx::Error>> {
+        Box::pin(futures::future::ok(&mut self.inner))
+    }
+
+    #[track_caller]
+    fn begin(self) -> BoxFuture<'t, Result<crate::Transaction<'t, DB>, sqlx::Error>> {
+        Box::pin(crate::Transaction::<'c, DB>::begin(self))
+    }
+}
+
+/// Begins a SQLx transaction, or a savepoint, in a `sqlx.begin` span.
+async fn begin<'c, DB>(
+    conn: impl Into<sqlx::pool::MaybePoolConnection<'c, DB>>,
+    parent: Option<tracing::Id>,
+    sql: &str,
+    attributes: &std::sync::Arc<crate::Attributes>,
+) -> Result<sqlx::Transaction<'c, DB>, sqlx::Error>
+where
+    DB: crate::prelude::Database,
+{
+    let (span, timer) = crate::span::query_span!(parent: parent, "sqlx.begin", sql, attributes);
+    let fut = timer.watch(sqlx::Transaction::begin(conn, None));
+    async move { fut.await.inspect_err(crate::span::record_error::<DB>) }
+        .instrument(span)
+        .await
+}
+
+/// Acquires a connection from the traced pool, handing out the SQLx connection: only the
+/// acquisition and the `BEGIN` statement are traced, use [`Acquire`] to trace the queries.
+impl<'a, DB> sqlx::Acquire<'a> for &'_ crate::Pool<DB>
+where
+    DB: crate::prelude::Database,
+{
+    type Database = DB;
+
+    type Connection = sqlx::pool::PoolConnection<DB>;
+
+    fn acquire(self) -> BoxFuture<'static, Result<Self::Connection, sqlx::Error>> {
+        let pool = self.clone();
+        Box::pin(async move { pool.acquire_traced().await })
+    }
+
+    fn begin(self) -> BoxFuture<'static, Result<sqlx::Transaction<'a, DB>, sqlx::Error>> {
+        let pool = self.clone();
+        Box::pin(async move {
+            let conn = pool.acquire_traced().await?;
+            let conn = sqlx::pool::MaybePoolConnection::PoolConnection(conn);
+            let parent = tracing::Span::current().id();
+            begin(conn, parent, "BEGIN", &pool.attributes).await
+        })
+    }
+}
+
+/// Hands out the driver connection of the traced pool connection: only the `BEGIN` statement
+/// is traced, use [`Acquire`] to trace the queries.
+impl<'c, DB> sqlx::Acquire<'c> for &'c mut crate::PoolConnection<DB>
+where
+    DB: crate::prelude::Database,
+{
+    type Database = DB;
+
+    type Connection = &'c mut DB::Connection;
+
+    fn acquire(self) -> BoxFuture<'c, Result<Self::Connection, sqlx::Error>> {
+        Box::pin(futures::future::ok(self.inner.inner.as_mut()))
+    }
+
+    fn begin(self) -> BoxFuture<'c, Result<sqlx::Transaction<'c, DB>, sqlx::Error>> {
+        let parent = tracing::Span::current().id();
+        Box::pin(begin(
+            self.inner.inner.as_mut(),
+            parent,
+            "BEGIN",
+            &self.inner.attributes,
+        ))
+    }
+}
+
+/// Hands out the driver connection of the traced transaction: only the `BEGIN` statement of
+/// savepoints is traced, use [`Acquire`] to trace the queries.
+impl<'c, 't, DB> sqlx::Acquire<'t> for &'t mut crate::Transaction<'c, DB>
+where
+    DB: crate::prelude::Database,
+{
+    type Database = DB;
+
+    type Connection = &'t mut DB::Connection;
+
+    fn acquire(self) -> BoxFuture<'t, Result<Self::Connection, sqlx::Error>> {
+        Box::pin(futures::future::ok(&mut *self.inner.inner))
+    }
+
+    fn begin(self) -> BoxFuture<'t, Result<sqlx::Transaction<'t, DB>, sqlx::Error>> {
+        let parent = self.span.parent();
+        let sql = self.span.savepoint_sql();
+        Box::pin(async move {
+            begin(&mut *self.inner.inner, parent, &sql, &self.inner.attributes).await
+        })
+    }
+}
@@ -0,0 +1,169 @@
+//! Classification of SQLx errors into the low-cardinality values recorded as `error.type`.
+//!
+//! | Error                                        | `error.type`                 |
+//! |----------------------------------------------|------------------------------|
+//! | `Error::Configuration`                       | `configuration`              |
+//! | `Error::InvalidArgument`                     | `invalid_argument`           |
+//! | `Error::Database` with `UniqueViolation`     | `unique_violation`           |
+//! | `Error::Database` with `ForeignKeyViolation` | `foreign_key_violation`      |
+//! | `Error::Database` with `NotNullViolation`    | `not_null_violation`         |
+//! | `Error::Database` with `CheckViolation`      | `check_violation`            |
+//! | other `Error::Database`                      | status code, or `database`   |
+//! | `Error::Io`                                  | `io`                         |
+//! | `Error::Tls`                                 | `tls`                        |
+//! | `Error::Protocol`                            | `protocol`                   |
+//! | `Error::RowNotFound`                         | `row_not_found`              |
+//! | `Error::TypeNotFound`                        | `type_not_found`             |
+//! | `Error::ColumnIndexOutOfBounds`              | `column_index_out_of_bounds` |
+//! | `Error::ColumnNotFound`                      | `column_not_found`           |
+//! | `Error::ColumnDecode`, `Error::Decode`       | `decode`                     |
+//! | `Error::Encode`                              | `encode`                     |
+//! | `Error::AnyDriverError`                      | `driver`                     |
+//! | `Error::PoolTimedOut`                        | `pool_timed_out`             |
+//! | `Error::PoolClosed`                          | `pool_closed`                |
+//! | `Error::WorkerCrashed`                       | `worker_crashed`             |
+//! | `Error::InvalidSavePointStatement`           | `invalid_savepoint`          |
+//! | `Error::BeginFailed`                         | `begin_failed`               |
+//! | anything else                                | `other`                      |
+
+use sqlx::error::ErrorKind;
+
+/// Returns the value to record as `error.type` for the given error.
+///
+/// The status code, when the driver provides one, is used for database errors that
+/// don't match a known constraint violation.
+pub(crate) fn error_type<'a>(err: &sqlx::Error, status_code: Option<&'a str>) -> &'a str {
+    match err {
+        sqlx::Error::Configuration(_) => "configuration",
+        sqlx::Error::InvalidArgument(_) => "invalid_argument",
+        sqlx::Error::Database(inner) => match inner.kind() {
+            ErrorKind::UniqueViolation => "unique_violation",
+            ErrorKind::ForeignKeyViolation => "foreign_key_violation",
+            ErrorKind::NotNullViolation => "not_null_violation",
+            ErrorKind::CheckViolation => "check_violation",
+            _ => status_code.unwrap_or("database"),
+        },
+        sqlx::Error::Io(_) => "io",
+        sqlx::Error::Tls(_) => "tls",
+        sqlx::Error::Protocol(_) => "protocol",
+        sqlx::Error::RowNotFound => "row_not_found",
+        sqlx::Error::TypeNotFound { .. } => "type_not_found",
+        sqlx::Error::ColumnIndexOutOfBounds { .. } => "column_index_out_of_bounds",
+        sqlx::Error::ColumnNotFound(_) => "column_not_found",
+        sqlx::Error::ColumnDecode { .. } | sqlx::Error::Decode(_) => "decode",
+        sqlx::Error::Encode(_) => "encode",
+        sqlx::Error::AnyDriverError(_) => "driver",
+        sqlx::Error::PoolTimedOut => "pool_timed_out",
+        sqlx::Error::PoolClosed => "pool_closed",
+        sqlx::Error::WorkerCrashed => "worker_crashed",
+        sqlx::Error::InvalidSavePointStatement => "invalid_savepoint",
+        sqlx::Error::BeginFailed => "begin_failed",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use sqlx::error::{DatabaseError, ErrorKind};
+
+    use super::error_type;
+
+    #[derive(Debug)]
+    struct FakeError(ErrorKind);
+
+    impl std::fmt::Display for FakeError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "fake error {:?}", self.0)
+        }
+    }
+
+    impl std::error::Error for FakeError {}
+
+    impl DatabaseError for FakeError {
+        fn message(&self) -> &str {
+            "fake error"
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed("23505"))
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> ErrorKind {
+            match self.0 {
+                ErrorKind::UniqueViolation => ErrorKind::UniqueViolation,
+                ErrorKind::ForeignKeyViolation => ErrorKind::ForeignKeyViolation,
+                ErrorKind::NotNullViolation => ErrorKind::NotNullViolation,
+                ErrorKind::CheckViolation => ErrorKind::CheckViolation,
+                _ => ErrorKind::Other,
+            }
+        }
+    }
+
+    fn database(kind: ErrorKind) -> sqlx::Error {
+        sqlx::Error::Database(Box::new(FakeError(kind)))
+    }
+
+    #[test]
+    fn should_classify_database_errors() {
+        let cases = [
+            (ErrorKind::UniqueViolation, "unique_violation"),
+            (ErrorKind::ForeignKeyViolation, "foreign_key_violation"),
+            (ErrorKind::NotNullViolation, "not_null_violation"),
+            (ErrorKind::CheckViolation, "check_violation"),
+        ];
+        for (kind, expected) in cases {
+            assert_eq!(error_type(&database(kind), Some("23505")), expected);
+        }
+        assert_eq!(
+            error_type(&database(ErrorKind::Other), Some("40001")),
+            "40001"
+        );
+        assert_eq!(error_type(&database(ErrorKind::Other), None), "database");
+    }
+
+    #[test]
+    fn should_classify_client_errors() {
+        let cases = [
+            (sqlx::Error::PoolTimedOut, "pool_timed_out"),
+            (sqlx::Error::PoolClosed, "pool_closed"),
+            (
+                sqlx::Error::Io(std::io::ErrorKind::ConnectionReset.into()),
+                "io",
+            ),
+            (sqlx::Error::Tls("handshake".into()), "tls"),
+            (sqlx::Error::Protocol("unexpected".into()), "protocol"),
+            (
+                sqlx::Error::Configuration("invalid".into()),
+                "configuration",
+            ),
+            (sqlx::Error::RowNotFound, "row_not_found"),
+            (sqlx::Error::Decode("invalid".into()), "decode"),
+            (
+                sqlx::Error::ColumnDecode {
+                    index: "0".into(),
+                    source: "invalid".into(),
+                },
+                "decode",
+            ),
+            (sqlx::Error::ColumnNotFound("id".into()), "column_not_found"),
+            (sqlx::Error::WorkerCrashed, "worker_crashed"),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(error_type(&err, None), expected);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Lightweight SQL inspection, extracting the operation and the primary collection
+//! of a statement to populate `db.operation.name` and `db.collection.name`.
+
+use crate::lexer::{Lexer, Token};
+use crate::prelude::Dialect;
+
+/// Operation and collection extracted from a SQL statement.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Statement<'a> {
+    /// Leading verb of the statement, in uppercase.
+    pub operation: Option<String>,
+    /// First collection targeted by the statement, as written in the query.
+    pub collection: Option<&'a str>,
+}
+
+/// Modifiers that can sit between a verb and the collection name.
+const MODIFIERS: &[&str] = &[
+    "ABORT",
+    "DELAYED",
+    "EXISTS",
+    "FAIL",
+    "GLOBAL",
+    "HIGH_PRIORITY",
+    "IF",
+    "IGNORE",
+    "LOCAL",
+    "LOW_PRIORITY",
+    "NOT",
+    "ONLY",
+    "OR",
+    "QUICK",
+    "REPLACE",
+    "ROLLBACK",
+    "TEMP",
+    "TEMPORARY",
+    "UNLOGGED",
+];
+
+/// Verbs that can follow the common table expressions of a `WITH` clause.
+const MAIN_VERBS: &[&str] = &["DELETE", "INSERT", "MERGE", "SELECT", "UPDATE"];
+
+/// Iterator over the meaningful tokens of a statement, with their offset.
+#[derive(Clone)]
+struct Tokens<'a> {
+    sql: &'a str,
+    lexer: Lexer<'a>,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(sql: &'a str, dialect: Dialect) -> Self {
+        Self {
+            sql,
+            lexer: Lexer::new(sql, dialect),
+        }
+    }
+
+    /// Moves after the next keyword, at the current nesting level, matching one of the candidates.
+    fn find_keyword(&mut self, candidates: &[&str]) -> Option<&'a str> {
+        let mut depth = 0usize;
+        for (token, text, _) in self.by_ref() {
+            match (token, text) {
+                (Token::Symbol, "(") => depth += 1,
+                (Token::Symbol, ")") => depth = depth.checked_sub(1)?,
+                (Token::Symbol, ";") if depth == 0 => return None,
+                (Token::Word, _) if depth == 0 && is_one_of(text, candidates) => return Some(text),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Reads a possibly qualified collection name, skipping the leading modifiers.
+    fn collection(&mut self) -> Option<&'a str> {
+        let (mut token, mut text, mut start) = self.next()?;
+        while token == Token::Word && is_one_of(text, MODIFIERS) {
+            (token, text, start) = self.next()?;
+        }
+        if !matches!(token, Token::Word | Token::Quoted) {
+            return None;
+        }
+        let mut end = start + text.len();
+        loop {
+            let mut lookahead = self.clone();
+            match (lookahead.next(), lookahead.next()) {
+                (
+                    Some((Token::Symbol, ".", _)),
+                    Some((Token::Word | Token::Quoted, part, from)),
+                ) => {
+                    end = from + part.len();
+                    *self = lookahead;
+                }
+                _ => return Some(&self.sql[start..end]),
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = (Token, &'a str, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.lexer.pos;
+            match self.lexer.next()? {
+                (Token::Whitespace | Token::Comment, _) => continue,
+                (token, text) => return Some((token, text, start)),
+            }
+        }
+    }
+}
+
+fn is_one_of(word: &str, candidates: &[&str]) -> bool {
+    candidates.iter().any(|c| c.eq_ignore_ascii_case(word))
+}
+
+/// Extracts the operation and primary collection of a SQL statement.
+pub(crate) fn inspect(sql: &str, dialect: Dialect) -> Statement<'_> {
+    let mut tokens = Tokens::new(sql, dialect);
+    let verb = loop {
+        match tokens.next() {
+            Some((Token::Symbol, "(", _)) => continue,
+            Some((Token::Word, text, _)) => break text,
+            _ => return Statement::default(),
+        }
+    };
+    let verb = if verb.eq_ignore_ascii_case("with") {
+        tokens.find_keyword(MAIN_VERBS).unwrap_or(verb)
+    } else {
+        verb
+    };
+    let operation = verb.to_ascii_uppercase();
+    let collection = match operation.as_str() {
+        "SELECT" | "DELETE" => tokens
+            .find_keyword(&["FROM"])
+            .and_then(|_| tokens.collection()),
+        "INSERT" | "REPLACE" | "MERGE" => tokens
+            .find_keyword(&["INTO"])
+            .and_then(|_| tokens.collection()),
+        "UPDATE" => tokens.collection(),
+        "CREATE" | "DROP" | "ALTER" | "TRUNCATE" => {
+            let mut lookahead = tokens.clone();
+            match lookahead.find(|(_, text, _)| !is_one_of(text, MODIFIERS)) {
+                Some((Token::Word, text, _)) if text.eq_ignore_ascii_case("table") => {
+                    lookahead.collection()
+                }
+                _ if operation == "TRUNCATE" => tokens.collection(),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    Statement {
+        operation: Some(operation),
+        collection,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::inspect;
+    use crate::prelude::Dialect;
+
+    fn check(sql: &str, operation: &str, collection: Option<&str>) {
+        let statement = inspect(sql, Dialect::Postgres);
+        assert_eq!(statement.operation.as_deref(), Some(operation), "{sql}");
+        assert_eq!(statement.collection, collection, "{sql}");
+    }
+
+    #[test]
+    fn should_extract_operation_and_collection() {
+        check("select 1", "SELECT", None);
+        check(
+            "SELECT extract(year FROM created_at) FROM public.users u JOIN roles r ON true",
+            "SELECT",
+            Some("public.users"),
+        );
+        check("select * from (select 1) as t", "SELECT", None);
+        check(
+            "INSERT INTO \"Users\" (id, name) VALUES ($1, $2)",
+            "INSERT",
+            Some("\"Users\""),
+        );
+        check(
+            "insert or replace into users values (?)",
+            "INSERT",
+            Some("users"),
+        );
+        check("UPDATE ONLY users SET name = $1", "UPDATE", Some("users"));
+        check("delete from users where id = $1", "DELETE", Some("users"));
+        check("CALL refresh_stats($1)", "CALL", None);
+        check(
+            "CREATE TABLE IF NOT EXISTS users (id int)",
+            "CREATE",
+            Some("users"),
+        );
+        check("create index idx on users (id)", "CREATE", None);
+        check("truncate table users", "TRUNCATE", Some("users"));
+        check("BEGIN", "BEGIN", None);
+    }
+
+    #[test]
+    fn should_look_through_common_table_expressions() {
+        check(
+            "WITH recent AS (SELECT * FROM events WHERE at > now()) SELECT * FROM recent",
+            "SELECT",
+            Some("recent"),
+        );
+        check(
+            "with a as (select 1), b as (select 2) delete from users where id in (select * from a)",
+            "DELETE",
+            Some("users"),
+        );
+    }
+
+    #[test]
+    fn should_ignore_empty_statements() {
+        assert_eq!(inspect("  -- nothing", Dialect::Sqlite), Default::default());
+    }
+}
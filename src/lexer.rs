@@ -0,0 +1,221 @@
+use crate::prelude::Dialect;
+
+/// Kind of token produced by the [`Lexer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Token {
+    Whitespace,
+    Comment,
+    Literal,
+    Parameter,
+    Word,
+    Quoted,
+    Symbol,
+}
+
+/// Minimal SQL lexer, only precise enough to tell literals apart from the rest.
+#[derive(Clone)]
+pub(crate) struct Lexer<'a> {
+    sql: &'a str,
+    pub(crate) pos: usize,
+    dialect: Dialect,
+}
+
+fn is_ident(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' || !byte.is_ascii()
+}
+
+impl<'a> Lexer<'a> {
+    pub(crate) fn new(sql: &'a str, dialect: Dialect) -> Self {
+        Self {
+            sql,
+            pos: 0,
+            dialect,
+        }
+    }
+
+    fn byte(&self, offset: usize) -> Option<u8> {
+        self.sql.as_bytes().get(self.pos + offset).copied()
+    }
+
+    /// Advances while the predicate holds, starting at the given offset.
+    fn skip_while(&self, mut offset: usize, predicate: impl Fn(u8) -> bool) -> usize {
+        while self.byte(offset).is_some_and(&predicate) {
+            offset += 1;
+        }
+        offset
+    }
+
+    /// Length of a quoted section starting at the current position.
+    fn quoted(&self, start: usize, close: u8, backslash: bool) -> usize {
+        let mut offset = start + 1;
+        while let Some(byte) = self.byte(offset) {
+            if backslash && byte == b'\\' {
+                offset += 2;
+            } else if byte == close {
+                if self.byte(offset + 1) == Some(close) {
+                    offset += 2;
+                } else {
+                    return offset + 1;
+                }
+            } else {
+                offset += 1;
+            }
+        }
+        self.sql.len() - self.pos
+    }
+
+    fn block_comment(&self) -> usize {
+        let nested = self.dialect == Dialect::Postgres;
+        let mut depth = 0;
+        let mut offset = 0;
+        while let Some(byte) = self.byte(offset) {
+            match (byte, self.byte(offset + 1)) {
+                (b'/', Some(b'*')) if depth == 0 || nested => {
+                    depth += 1;
+                    offset += 2;
+                }
+                (b'*', Some(b'/')) => {
+                    depth -= 1;
+                    offset += 2;
+                    if depth == 0 {
+                        return offset;
+                    }
+                }
+                _ => offset += 1,
+            }
+        }
+        offset
+    }
+
+    fn number(&self) -> usize {
+        if self.byte(0) == Some(b'0') && matches!(self.byte(1), Some(b'x' | b'X')) {
+            return self.skip_while(2, |b| b.is_ascii_hexdigit() || b == b'_');
+        }
+        let mut offset = self.skip_while(0, |b| b.is_ascii_digit() || b == b'_');
+        if self.byte(offset) == Some(b'.') {
+            offset = self.skip_while(offset + 1, |b| b.is_ascii_digit() || b == b'_');
+        }
+        if matches!(self.byte(offset), Some(b'e' | b'E')) {
+            let sign = usize::from(matches!(self.byte(offset + 1), Some(b'+' | b'-')));
+            if self
+                .byte(offset + 1 + sign)
+                .is_some_and(|b| b.is_ascii_digit())
+            {
+                offset = self.skip_while(offset + 1 + sign, |b| b.is_ascii_digit());
+            }
+        }
+        offset
+    }
+
+    /// Length of a postgres dollar-quoted string, if one starts at the current position.
+    fn dollar_quoted(&self) -> Option<usize> {
+        let end = self.skip_while(1, |b| b.is_ascii_alphanumeric() || b == b'_');
+        if self.byte(end) != Some(b'$') {
+            return None;
+        }
+        let tag = &self.sql[self.pos..self.pos + end + 1];
+        let body = self.pos + tag.len();
+        Some(match self.sql[body..].find(tag) {
+            Some(index) => body + index + tag.len() - self.pos,
+            None => self.sql.len() - self.pos,
+        })
+    }
+
+    fn next_token(&self) -> Option<(Token, usize)> {
+        let dialect = self.dialect;
+        let byte = self.byte(0)?;
+        let next = self.byte(1);
+        Some(match byte {
+            b if b.is_ascii_whitespace() => (
+                Token::Whitespace,
+                self.skip_while(0, |b| b.is_ascii_whitespace()),
+            ),
+            b'-' if next == Some(b'-')
+                && (dialect != Dialect::MySql
+                    || self.byte(2).is_none_or(|b| b.is_ascii_whitespace())) =>
+            {
+                (Token::Comment, self.skip_while(0, |b| b != b'\n'))
+            }
+            b'#' if dialect == Dialect::MySql => {
+                (Token::Comment, self.skip_while(0, |b| b != b'\n'))
+            }
+            b'/' if next == Some(b'*') => (Token::Comment, self.block_comment()),
+            b'\'' => (
+                Token::Literal,
+                self.quoted(0, b'\'', dialect == Dialect::MySql),
+            ),
+            b'"' if dialect == Dialect::MySql => (Token::Literal, self.quoted(0, b'"', true)),
+            b'"' => (Token::Quoted, self.quoted(0, b'"', false)),
+            b'`' => (Token::Quoted, self.quoted(0, b'`', false)),
+            b'[' if dialect == Dialect::Sqlite => (
+                Token::Quoted,
+                (self.skip_while(1, |b| b != b']') + 1).min(self.sql.len() - self.pos),
+            ),
+            b'e' | b'E' if next == Some(b'\'') && dialect == Dialect::Postgres => {
+                (Token::Literal, self.quoted(1, b'\'', true))
+            }
+            b'b' | b'B' | b'x' | b'X' | b'n' | b'N' if next == Some(b'\'') => (
+                Token::Literal,
+                self.quoted(1, b'\'', dialect == Dialect::MySql),
+            ),
+            b'0'..=b'9' => (Token::Literal, self.number()),
+            b'.' if next.is_some_and(|b| b.is_ascii_digit()) => (Token::Literal, self.number()),
+            b'?' => (Token::Parameter, self.skip_while(1, |b| b.is_ascii_digit())),
+            b'$' if next.is_some_and(|b| b.is_ascii_digit()) => {
+                (Token::Parameter, self.skip_while(1, |b| b.is_ascii_digit()))
+            }
+            b'$' if dialect == Dialect::Postgres => match self.dollar_quoted() {
+                Some(length) => (Token::Literal, length),
+                None => (Token::Symbol, 1),
+            },
+            b'$' | b':' | b'@' if dialect == Dialect::Sqlite && next.is_some_and(is_ident) => {
+                (Token::Parameter, self.skip_while(1, is_ident))
+            }
+            b if is_ident(b) => (Token::Word, self.skip_while(0, is_ident)),
+            _ => (Token::Symbol, 1),
+        })
+    }
+
+    /// Skips whitespaces and comments.
+    pub(crate) fn skip_blank(&mut self) {
+        while let Some((Token::Whitespace | Token::Comment, length)) = self.next_token() {
+            self.pos += length;
+        }
+    }
+
+    /// Consumes a list of literals or parameters between parentheses, if there is one.
+    pub(crate) fn values_list(&self) -> Option<Self> {
+        let mut lexer = self.clone();
+        if lexer.next_token() != Some((Token::Symbol, 1)) || lexer.byte(0) != Some(b'(') {
+            return None;
+        }
+        lexer.pos += 1;
+        loop {
+            lexer.skip_blank();
+            match lexer.next_token()? {
+                (Token::Literal | Token::Parameter, length) => lexer.pos += length,
+                _ => return None,
+            }
+            lexer.skip_blank();
+            match lexer.byte(0)? {
+                b',' => lexer.pos += 1,
+                b')' => {
+                    lexer.pos += 1;
+                    return Some(lexer);
+                }
+                _ => return None,
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = (Token, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (token, length) = self.next_token()?;
+        let text = &self.sql[self.pos..self.pos + length];
+        self.pos += length;
+        Some((token, text))
+    }
+}
@@ -2,12 +2,30 @@
 
 use std::sync::Arc;
 
+use tracing::Instrument;
+
+mod acquire;
 mod connection;
+mod error;
+mod inspect;
+mod lexer;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod naming;
 mod pool;
 pub mod prelude;
+mod sanitize;
+mod semconv;
 pub(crate) mod span;
+mod standalone;
+mod stream;
+mod traced;
 mod transaction;
 
+pub use acquire::Acquire;
+pub use naming::{SpanNameContext, SpanNaming};
+pub use semconv::SemconvStability;
+
 #[cfg(feature = "postgres")]
 pub mod postgres;
 
@@ -19,12 +37,52 @@ pub mod mysql;
 
 /// Attributes describing the database connection and context.
 /// Used for span enrichment and attribute propagation.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 struct Attributes {
     name: Option<String>,
     host: Option<String>,
     port: Option<u16>,
     database: Option<String>,
+    sanitize: bool,
+    naming: SpanNaming,
+    stability: Option<SemconvStability>,
+    level: Option<tracing::Level>,
+    slow_query_threshold: Option<std::time::Duration>,
+    lifecycle_level: Option<tracing::Level>,
+    #[cfg(feature = "metrics")]
+    meter: Option<opentelemetry::metrics::Meter>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<metrics::Metrics>,
+}
+
+impl Attributes {
+    /// Reads the location of the database from the connect options of a driver.
+    fn from_options<DB: prelude::Database>(
+        options: &<DB::Connection as sqlx::Connection>::Options,
+    ) -> Self {
+        let server = DB::server(options);
+        Self {
+            host: server.host.map(String::from),
+            port: server.port,
+            database: server.database.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    /// Stability mode of the spans, read from the environment until set, as when connecting
+    /// before the pool is built.
+    fn stability(&self) -> SemconvStability {
+        self.stability.unwrap_or_else(SemconvStability::from_env)
+    }
+
+    /// Whether the duration of the queries is needed, to report slow queries or record metrics.
+    fn is_timed(&self) -> bool {
+        #[cfg(feature = "metrics")]
+        if self.metrics.is_some() {
+            return true;
+        }
+        self.slow_query_threshold.is_some()
+    }
 }
 
 /// Builder for constructing a [`Pool`] with custom attributes.
@@ -37,61 +95,55 @@ pub struct PoolBuilder<DB: sqlx::Database> {
     attributes: Attributes,
 }
 
-// URL-based attribute extraction — works for TCP-backed drivers (postgres, mysql).
-// Sqlite has its own impl below because `to_url_lossy()` panics on sqlite options.
-#[cfg(feature = "postgres")]
-impl From<sqlx::Pool<sqlx::Postgres>> for PoolBuilder<sqlx::Postgres> {
+impl<DB> From<sqlx::Pool<DB>> for PoolBuilder<DB>
+where
+    DB: prelude::Database,
+{
     /// Create a new builder from an existing SQLx pool.
-    fn from(pool: sqlx::Pool<sqlx::Postgres>) -> Self {
-        use sqlx::ConnectOptions;
-
-        let url = pool.connect_options().to_url_lossy();
-        let attributes = Attributes {
-            name: None,
-            host: url.host_str().map(String::from),
-            port: url.port(),
-            database: url
-                .path_segments()
-                .and_then(|mut segments| segments.next().map(String::from)),
-        };
+    ///
+    /// The attributes are read from the connect options of the pool.
+    fn from(pool: sqlx::Pool<DB>) -> Self {
+        let attributes = Attributes::from_options::<DB>(&pool.connect_options());
         Self { pool, attributes }
     }
 }
 
-#[cfg(feature = "sqlite")]
-impl From<sqlx::Pool<sqlx::Sqlite>> for PoolBuilder<sqlx::Sqlite> {
-    /// Create a new builder from an existing SQLx pool.
-    fn from(pool: sqlx::Pool<sqlx::Sqlite>) -> Self {
-        let attributes = Attributes {
-            name: None,
-            host: pool
-                .connect_options()
-                .get_filename()
-                .to_str()
-                .map(String::from),
-            port: None,
-            database: None,
-        };
-        Self { pool, attributes }
+impl<DB> PoolBuilder<DB>
+where
+    DB: prelude::Database,
+{
+    /// Connects to the given URL with the default pool options, returning a builder to
+    /// customize the attributes before building the traced pool.
+    ///
+    /// See [`PoolBuilder::connect_with`] for the tracing of the connect attempt.
+    pub async fn connect(url: &str) -> Result<Self, sqlx::Error> {
+        let options = url.parse()?;
+        Self::connect_with(sqlx::pool::PoolOptions::new(), options).await
     }
-}
 
-#[cfg(feature = "mysql")]
-impl From<sqlx::Pool<sqlx::MySql>> for PoolBuilder<sqlx::MySql> {
-    /// Create a new builder from an existing SQLx pool.
-    fn from(pool: sqlx::Pool<sqlx::MySql>) -> Self {
-        use sqlx::ConnectOptions;
-
-        let url = pool.connect_options().to_url_lossy();
-        let attributes = Attributes {
-            name: None,
-            host: url.host_str().map(String::from),
-            port: url.port(),
-            database: url
-                .path_segments()
-                .and_then(|mut segments| segments.next().map(String::from)),
-        };
-        Self { pool, attributes }
+    /// Connects to the database with the given options, returning a builder to customize the
+    /// attributes before building the traced pool.
+    ///
+    /// The connect attempt is traced in a `sqlx.connect` span, holding the location of the
+    /// database and the error when failing. The attributes are read from the connect options,
+    /// and can be overwritten with the setters of the builder.
+    pub async fn connect_with(
+        pool_options: sqlx::pool::PoolOptions<DB>,
+        options: <DB::Connection as sqlx::Connection>::Options,
+    ) -> Result<Self, sqlx::Error> {
+        let attributes = Attributes::from_options::<DB>(&options);
+        let span = standalone::lifecycle_span!("sqlx.connect", attributes);
+        let pool =
+            standalone::lifecycle::<DB, _, _>(span, pool_options.connect_with(options)).await?;
+        Ok(Self { pool, attributes })
+    }
+
+    /// Creates a builder from the given URL, the pool not connecting until the first query.
+    pub fn connect_lazy(url: &str) -> Result<Self, sqlx::Error> {
+        let options = url.parse()?;
+        let attributes = Attributes::from_options::<DB>(&options);
+        let pool = sqlx::pool::PoolOptions::new().connect_lazy_with(options);
+        Ok(Self { pool, attributes })
     }
 }
 
@@ -120,8 +172,81 @@ impl<DB: sqlx::Database> PoolBuilder<DB> {
         self
     }
 
+    /// Sanitize the query text before recording it in `db.query.text`.
+    ///
+    /// String and numeric literals are replaced by `?`, comments are removed and
+    /// `IN` lists are collapsed, so that values inlined in the SQL don't end up in
+    /// traces. Disabled by default.
+    pub fn with_query_sanitization(mut self, enabled: bool) -> Self {
+        self.attributes.sanitize = enabled;
+        self
+    }
+
+    /// Set the strategy used to name query spans.
+    ///
+    /// Defaults to [`SpanNaming::Semantic`], producing names like `SELECT users`.
+    pub fn with_span_naming(mut self, naming: SpanNaming) -> Self {
+        self.attributes.naming = naming;
+        self
+    }
+
+    /// Select which semantic conventions are emitted on spans.
+    ///
+    /// When not set, the mode is read from the `OTEL_SEMCONV_STABILITY_OPT_IN`
+    /// environment variable, see [`SemconvStability::from_env`].
+    pub fn with_semconv_stability(mut self, stability: SemconvStability) -> Self {
+        self.attributes.stability = Some(stability);
+        self
+    }
+
+    /// Set the level of the query spans, `INFO` by default.
+    ///
+    /// Query spans below the enabled level of the subscriber are skipped, without parsing
+    /// nor recording anything.
+    pub fn with_level(mut self, level: tracing::Level) -> Self {
+        self.attributes.level = Some(level);
+        self
+    }
+
+    /// Set the level of the connection and transaction lifecycle spans, `INFO` by default.
+    pub fn with_lifecycle_level(mut self, level: tracing::Level) -> Self {
+        self.attributes.lifecycle_level = Some(level);
+        self
+    }
+
+    /// Report the queries taking longer than the given threshold.
+    ///
+    /// Slow queries are marked with `db.slow_query = true` and emit a `WARN` event in their
+    /// span, holding the elapsed time, the operation and the sanitized query text.
+    pub fn with_slow_query_threshold(mut self, threshold: std::time::Duration) -> Self {
+        self.attributes.slow_query_threshold = Some(threshold);
+        self
+    }
+
+    /// Set the meter used to create the metric instruments.
+    ///
+    /// Defaults to a meter of the global provider, as installed when building the pool.
+    #[cfg(feature = "metrics")]
+    pub fn with_meter(mut self, meter: &opentelemetry::metrics::Meter) -> Self {
+        self.attributes.meter = Some(meter.clone());
+        self
+    }
+
     /// Build the [`Pool`] with the configured attributes.
-    pub fn build(self) -> Pool<DB> {
+    pub fn build(mut self) -> Pool<DB> {
+        self.attributes
+            .stability
+            .get_or_insert_with(SemconvStability::from_env);
+        #[cfg(feature = "metrics")]
+        {
+            let meter = self
+                .attributes
+                .meter
+                .take()
+                .unwrap_or_else(metrics::Metrics::global_meter);
+            let name = self.attributes.name.as_deref();
+            self.attributes.metrics = Some(metrics::Metrics::new(&meter, &self.pool, name));
+        }
         Pool {
             inner: self.pool,
             attributes: Arc::new(self.attributes),
@@ -166,36 +291,161 @@ where
 
 impl<DB> Pool<DB>
 where
-    DB: sqlx::Database,
+    DB: crate::prelude::Database,
 {
     /// Retrieves a connection and immediately begins a new transaction.
     ///
-    /// The returned [`Transaction`] is instrumented for tracing.
-    pub async fn begin<'c>(&'c self) -> Result<Transaction<'c, DB>, sqlx::Error> {
-        self.inner.begin().await.map(|inner| Transaction {
-            inner,
-            attributes: self.attributes.clone(),
-        })
+    /// The returned [`Transaction`] is instrumented for tracing, within a `db.transaction`
+    /// span covering the connection acquisition and living until it is committed, rolled
+    /// back or dropped. The caller location is kept to report transactions dropped without
+    /// commit or rollback.
+    #[track_caller]
+    pub fn begin(
+        &self,
+    ) -> impl Future<Output = Result<Transaction<'static, DB>, sqlx::Error>> + use<'_, DB> {
+        let location = std::panic::Location::caller();
+        async move {
+            let span = transaction::TransactionSpan::new::<DB>(&self.attributes, location);
+            let conn = self.acquire_traced().instrument(span.span().clone()).await;
+            span.begin(conn, &self.attributes).await
+        }
     }
 
     /// Attempts to retrieve a connection and immediately begins a new transaction if successful.
     ///
-    /// The returned [`Transaction`] is instrumented for tracing.
-    pub async fn try_begin<'c>(&'c self) -> Result<Option<Transaction<'c, DB>>, sqlx::Error> {
-        self.inner.try_begin().await.map(|t| {
-            t.map(|inner| Transaction {
-                inner,
-                attributes: self.attributes.clone(),
-            })
-        })
+    /// The returned [`Transaction`] is instrumented for tracing, like with [`Pool::begin`].
+    #[track_caller]
+    pub fn try_begin(
+        &self,
+    ) -> impl Future<Output = Result<Option<Transaction<'static, DB>>, sqlx::Error>> + use<'_, DB>
+    {
+        let location = std::panic::Location::caller();
+        async move {
+            let span = transaction::TransactionSpan::new::<DB>(&self.attributes, location);
+            let Some(conn) = span.span().in_scope(|| self.try_acquire_traced()) else {
+                span.unavailable();
+                return Ok(None);
+            };
+            span.begin(Ok(conn), &self.attributes).await.map(Some)
+        }
+    }
+
+    /// Acquires a connection from the inner pool, recording the time spent waiting for it.
+    fn acquire_connection(
+        &self,
+    ) -> impl Future<Output = Result<sqlx::pool::PoolConnection<DB>, sqlx::Error>> + Send + use<DB>
+    {
+        let pool = self.inner.clone();
+        #[cfg(feature = "metrics")]
+        let metrics = self.attributes.metrics.clone();
+        async move {
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = metrics {
+                return metrics.acquire(&pool).await;
+            }
+            pool.acquire().await
+        }
     }
 
     /// Acquires a pooled connection, instrumented for tracing.
     pub async fn acquire(&self) -> Result<PoolConnection<DB>, sqlx::Error> {
-        self.inner.acquire().await.map(|inner| PoolConnection {
-            attributes: self.attributes.clone(),
+        self.acquire_traced()
+            .await
+            .map(|inner| self.pool_connection(inner))
+    }
+
+    /// Attempts to acquire an idle connection, instrumented for tracing, without waiting.
+    pub fn try_acquire(&self) -> Option<PoolConnection<DB>> {
+        self.try_acquire_traced()
+            .map(|inner| self.pool_connection(inner))
+    }
+
+    fn pool_connection(&self, inner: sqlx::pool::PoolConnection<DB>) -> PoolConnection<DB> {
+        PoolConnection {
+            inner: Traced {
+                inner,
+                attributes: self.attributes.clone(),
+                transaction: None,
+            },
+        }
+    }
+
+    /// Shuts down the pool, see [`sqlx::Pool::close`].
+    ///
+    /// The pool is closed immediately, the returned future waits for the connections to be
+    /// closed, in a `sqlx.close` span recording the time spent in `db.client.connection.drain_time`.
+    pub fn close(&self) -> impl Future<Output = ()> + '_ {
+        let span = crate::span::dynamic_span!(
+            self.attributes
+                .lifecycle_level
+                .unwrap_or(tracing::Level::INFO),
+            "sqlx.close",
+            "db.client.connection.pool.name" = self.attributes.name.as_deref(),
+            "db.client.connection.pool.size" = self.inner.size(),
+            "db.client.connection.pool.idle" = self.inner.num_idle(),
+            "db.client.connection.drain_time" = tracing::field::Empty,
+        );
+        let started = std::time::Instant::now();
+        let close = span.in_scope(|| self.inner.close());
+        async move {
+            close.instrument(span.clone()).await;
+            span.record(
+                "db.client.connection.drain_time",
+                started.elapsed().as_secs_f64(),
+            );
+        }
+    }
+
+    /// Returns `true` if the pool has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    /// Returns the number of connections currently active, including idle ones.
+    pub fn size(&self) -> u32 {
+        self.inner.size()
+    }
+
+    /// Returns the number of idle connections.
+    pub fn num_idle(&self) -> usize {
+        self.inner.num_idle()
+    }
+
+    /// Returns the options the pool was built with.
+    pub fn options(&self) -> &sqlx::pool::PoolOptions<DB> {
+        self.inner.options()
+    }
+
+    /// Returns the connect options used to open new connections.
+    pub fn connect_options(&self) -> Arc<<DB::Connection as sqlx::Connection>::Options> {
+        self.inner.connect_options()
+    }
+
+    /// Updates the connect options used to open new connections.
+    ///
+    /// The tracing attributes, resolved when building the pool, are left unchanged.
+    pub fn set_connect_options(&self, options: <DB::Connection as sqlx::Connection>::Options) {
+        self.inner.set_connect_options(options);
+    }
+
+    /// Wraps any SQLx executor, like a driver connection or a SQLx transaction, to instrument
+    /// the queries it runs with the attributes of this pool.
+    pub fn traced<E>(&self, inner: E) -> Traced<E> {
+        Traced {
             inner,
-        })
+            attributes: self.attributes.clone(),
+            transaction: None,
+        }
+    }
+
+    /// Returns the underlying SQLx pool, whose queries are not traced.
+    pub fn as_inner(&self) -> &sqlx::Pool<DB> {
+        &self.inner
+    }
+
+    /// Unwraps the underlying SQLx pool, whose queries are not traced.
+    pub fn into_inner(self) -> sqlx::Pool<DB> {
+        self.inner
     }
 }
 
@@ -206,8 +456,7 @@ pub struct Connection<'c, DB>
 where
     DB: sqlx::Database,
 {
-    inner: &'c mut DB::Connection,
-    attributes: Arc<Attributes>,
+    inner: Traced<&'c mut DB::Connection>,
 }
 
 impl<'c, DB: sqlx::Database> std::fmt::Debug for Connection<'c, DB> {
@@ -216,6 +465,19 @@ impl<'c, DB: sqlx::Database> std::fmt::Debug for Connection<'c, DB> {
     }
 }
 
+/// A SQLx executor instrumented for tracing.
+///
+/// Wraps any [`sqlx::Executor`], with [`Traced::new`] or [`Pool::traced`], and is itself an
+/// executor creating a span for each query. When holding a connection, `&mut Traced<_>` can be
+/// used as an executor as well: [`PoolConnection`], [`Transaction`] and [`TracedConnection`]
+/// dereference to it, so that `&mut *conn` and `&mut *tx` stay instrumented, as with SQLx. It
+/// dereferences itself to the wrapped connection, whose queries are not traced.
+pub struct Traced<C> {
+    inner: C,
+    attributes: Arc<Attributes>,
+    transaction: Option<transaction::TransactionScope>,
+}
+
 /// A pooled SQLx connection instrumented for tracing.
 ///
 /// Implements [`sqlx::Executor`] and propagates tracing attributes.
@@ -224,8 +486,19 @@ pub struct PoolConnection<DB>
 where
     DB: sqlx::Database,
 {
-    inner: sqlx::pool::PoolConnection<DB>,
-    attributes: Arc<Attributes>,
+    inner: Traced<sqlx::pool::PoolConnection<DB>>,
+}
+
+/// A standalone SQLx connection, opened outside of a pool, instrumented for tracing.
+///
+/// Implements [`sqlx::Executor`] and dereferences to a [`Traced`] connection, like a
+/// [`PoolConnection`]. With the `metrics` feature, the duration of its operations is recorded
+/// with the global meter provider, the connection state metrics being only reported for pools.
+pub struct TracedConnection<DB>
+where
+    DB: sqlx::Database,
+{
+    inner: Traced<Box<DB::Connection>>,
 }
 
 /// An in-progress database transaction or savepoint, instrumented for tracing.
@@ -236,6 +509,6 @@ pub struct Transaction<'c, DB>
 where
     DB: sqlx::Database,
 {
-    inner: sqlx::Transaction<'c, DB>,
-    attributes: Arc<Attributes>,
+    inner: Traced<sqlx::Transaction<'c, DB>>,
+    span: transaction::TransactionSpan,
 }
@@ -0,0 +1,231 @@
+//! OpenTelemetry metrics following the database client semantic conventions.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::{AsyncInstrument, Histogram, Meter};
+
+/// Bucket boundaries advised by the conventions for duration histograms, in seconds.
+const DURATION_BOUNDARIES: &[f64] = &[0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0];
+
+/// Connection counts of a pool.
+struct Usage {
+    size: u32,
+    idle: u32,
+    max: u32,
+}
+
+/// State of a pool observed by the connection counters.
+struct PoolState {
+    /// Pool name, when set on the builder.
+    labels: Vec<KeyValue>,
+    usage: Box<dyn Fn() -> Usage + Send + Sync>,
+    /// Number of tasks waiting for a connection.
+    pending: AtomicU64,
+}
+
+impl std::fmt::Debug for PoolState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PoolState")
+            .field("labels", &self.labels)
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Decrements the pending requests counter when dropped, even if the acquire is cancelled.
+struct Pending<'a>(&'a AtomicU64);
+
+impl<'a> Pending<'a> {
+    fn new(counter: &'a AtomicU64) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Instruments shared by the connections of a pool, or by a traced executor.
+#[derive(Clone, Debug)]
+pub(crate) struct Metrics {
+    operation_duration: Histogram<f64>,
+    pool: Option<PoolMetrics>,
+}
+
+/// Instruments reporting the state of a pool.
+#[derive(Clone, Debug)]
+struct PoolMetrics {
+    wait_time: Histogram<f64>,
+    state: Arc<PoolState>,
+}
+
+impl Metrics {
+    /// Creates the instrument recording the duration of the operations, for an executor
+    /// outside of a pool.
+    pub(crate) fn operations(meter: &Meter) -> Self {
+        Self {
+            operation_duration: meter
+                .f64_histogram("db.client.operation.duration")
+                .with_unit("s")
+                .with_description("Duration of database client operations.")
+                .with_boundaries(DURATION_BOUNDARIES.to_vec())
+                .build(),
+            pool: None,
+        }
+    }
+
+    /// Creates the instruments of the given pool.
+    ///
+    /// Counter callbacks can't be unregistered, they only hold a weak reference to the pool
+    /// state so that the pool is released once every traced handle is dropped.
+    pub(crate) fn new<DB: sqlx::Database>(
+        meter: &Meter,
+        pool: &sqlx::Pool<DB>,
+        name: Option<&str>,
+    ) -> Self {
+        let labels = name
+            .map(|name| KeyValue::new("db.client.connection.pool.name", name.to_string()))
+            .into_iter()
+            .collect();
+        let pool = pool.clone();
+        let state = Arc::new(PoolState {
+            labels,
+            usage: Box::new(move || Usage {
+                size: pool.size(),
+                idle: u32::try_from(pool.num_idle()).unwrap_or(u32::MAX),
+                max: pool.options().get_max_connections(),
+            }),
+            pending: AtomicU64::new(0),
+        });
+        observe(
+            meter,
+            "db.client.connection.count",
+            "{connection}",
+            "The number of connections that are currently in state described by the state attribute.",
+            &state,
+            |state, usage, observer| {
+                for (value, name) in [
+                    (usage.idle, "idle"),
+                    (usage.size.saturating_sub(usage.idle), "used"),
+                ] {
+                    let mut labels = state.labels.clone();
+                    labels.push(KeyValue::new("db.client.connection.state", name));
+                    observer.observe(i64::from(value), &labels);
+                }
+            },
+        );
+        observe(
+            meter,
+            "db.client.connection.max",
+            "{connection}",
+            "The maximum number of open connections allowed.",
+            &state,
+            |state, usage, observer| observer.observe(i64::from(usage.max), &state.labels),
+        );
+        observe(
+            meter,
+            "db.client.connection.pending_requests",
+            "{request}",
+            "The number of current pending requests for an open connection.",
+            &state,
+            |state, _, observer| {
+                let pending = state.pending.load(Ordering::Relaxed);
+                observer.observe(i64::try_from(pending).unwrap_or(i64::MAX), &state.labels);
+            },
+        );
+        Self {
+            pool: Some(PoolMetrics {
+                wait_time: meter
+                    .f64_histogram("db.client.connection.wait_time")
+                    .with_unit("s")
+                    .with_description(
+                        "The time it took to obtain an open connection from the pool.",
+                    )
+                    .with_boundaries(DURATION_BOUNDARIES.to_vec())
+                    .build(),
+                state,
+            }),
+            ..Self::operations(meter)
+        }
+    }
+
+    /// Acquires a connection from the pool, counting the pending request and recording
+    /// the time spent waiting.
+    pub(crate) async fn acquire<DB: sqlx::Database>(
+        &self,
+        pool: &sqlx::Pool<DB>,
+    ) -> Result<sqlx::pool::PoolConnection<DB>, sqlx::Error> {
+        let Some(metrics) = &self.pool else {
+            return pool.acquire().await;
+        };
+        let _pending = Pending::new(&metrics.state.pending);
+        let started = std::time::Instant::now();
+        let result = pool.acquire().await;
+        metrics
+            .wait_time
+            .record(started.elapsed().as_secs_f64(), &metrics.state.labels);
+        result
+    }
+
+    /// Meter of the global provider, scoped to this crate.
+    pub(crate) fn global_meter() -> Meter {
+        let scope = opentelemetry::InstrumentationScope::builder(env!("CARGO_PKG_NAME"))
+            .with_version(env!("CARGO_PKG_VERSION"))
+            .build();
+        opentelemetry::global::meter_with_scope(scope)
+    }
+
+    /// Records the duration of a query, with the same low-cardinality attributes as its span.
+    pub(crate) fn record_operation<DB: crate::prelude::Database>(
+        &self,
+        duration: std::time::Duration,
+        attributes: &crate::Attributes,
+        statement: &crate::inspect::Statement<'_>,
+        error_type: Option<&str>,
+    ) {
+        let mut labels = vec![KeyValue::new("db.system.name", DB::SYSTEM)];
+        let optional = [
+            ("db.namespace", attributes.database.as_deref()),
+            ("db.operation.name", statement.operation.as_deref()),
+            ("db.collection.name", statement.collection),
+            ("server.address", attributes.host.as_deref()),
+            ("error.type", error_type),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                labels.push(KeyValue::new(key, value.to_string()));
+            }
+        }
+        self.operation_duration
+            .record(duration.as_secs_f64(), &labels);
+    }
+}
+
+/// Registers an up-down counter reporting the pool state, as long as the pool is alive.
+fn observe<F>(
+    meter: &Meter,
+    name: &'static str,
+    unit: &'static str,
+    description: &'static str,
+    state: &Arc<PoolState>,
+    callback: F,
+) where
+    F: Fn(&PoolState, &Usage, &dyn AsyncInstrument<i64>) + Send + Sync + 'static,
+{
+    let state: Weak<PoolState> = Arc::downgrade(state);
+    meter
+        .i64_observable_up_down_counter(name)
+        .with_unit(unit)
+        .with_description(description)
+        .with_callback(move |observer| {
+            if let Some(state) = state.upgrade() {
+                callback(&state, &(state.usage)(), observer);
+            }
+        })
+        .build();
+}
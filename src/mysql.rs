@@ -1,3 +1,31 @@
 impl crate::prelude::Database for sqlx::MySql {
     const SYSTEM: &'static str = "mysql";
+    const DIALECT: crate::prelude::Dialect = crate::prelude::Dialect::MySql;
+
+    fn rows_affected(result: &sqlx::mysql::MySqlQueryResult) -> u64 {
+        result.rows_affected()
+    }
+
+    fn server(options: &sqlx::mysql::MySqlConnectOptions) -> crate::prelude::Server<'_> {
+        crate::prelude::Server {
+            host: Some(options.get_host()),
+            port: Some(options.get_port()),
+            database: options.get_database(),
+        }
+    }
+
+    /// The MySQL error number, rather than the SQLSTATE.
+    fn status_code(error: &dyn sqlx::error::DatabaseError) -> Option<String> {
+        error
+            .try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>()
+            .map(|error| error.number().to_string())
+    }
+
+    fn record_error_details(span: &tracing::Span, error: &dyn sqlx::error::DatabaseError) {
+        let Some(error) = error.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>() else {
+            return;
+        };
+        span.record("db.mysql.error.number", error.number());
+        span.record("db.mysql.error.sql_state", error.code());
+    }
 }
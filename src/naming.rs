@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+/// Information available when naming the span of a query.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct SpanNameContext<'a> {
+    /// Name of the instrumented SQLx method (e.g., "sqlx.fetch_one").
+    pub method: &'static str,
+    /// Database system (e.g., "postgresql").
+    pub system: &'static str,
+    /// Operation parsed from the statement (e.g., "SELECT").
+    pub operation: Option<&'a str>,
+    /// Collection parsed from the statement (e.g., "users").
+    pub collection: Option<&'a str>,
+    /// Database name configured on the pool.
+    pub namespace: Option<&'a str>,
+}
+
+/// Strategy used to name the span of every query.
+///
+/// Span names are exported through the `otel.name` field, the `tracing` span name
+/// always remains the SQLx method name.
+#[derive(Clone, Default)]
+pub enum SpanNaming {
+    /// Follows the OpenTelemetry database conventions: `{db.operation.name} {target}`,
+    /// where the target is the collection or, when not available, the database name.
+    #[default]
+    Semantic,
+    /// Uses the SQLx method name (e.g., "sqlx.fetch_one").
+    Method,
+    /// Uses a custom function.
+    Custom(Arc<dyn Fn(&SpanNameContext<'_>) -> String + Send + Sync>),
+}
+
+impl std::fmt::Debug for SpanNaming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Semantic => f.write_str("Semantic"),
+            Self::Method => f.write_str("Method"),
+            Self::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl SpanNaming {
+    /// Creates a strategy naming spans with the given function.
+    pub fn custom<F>(func: F) -> Self
+    where
+        F: Fn(&SpanNameContext<'_>) -> String + Send + Sync + 'static,
+    {
+        Self::Custom(Arc::new(func))
+    }
+
+    /// Computes the span name, `None` meaning the SQLx method name is kept.
+    pub(crate) fn name(&self, context: &SpanNameContext<'_>) -> Option<String> {
+        match self {
+            Self::Semantic => Some(semantic_name(context)),
+            Self::Method => None,
+            Self::Custom(func) => Some(func(context)),
+        }
+    }
+}
+
+fn semantic_name(context: &SpanNameContext<'_>) -> String {
+    let target = context.collection.or(context.namespace);
+    match (context.operation, target) {
+        (Some(operation), Some(target)) => format!("{operation} {target}"),
+        (Some(operation), None) => operation.to_string(),
+        (None, Some(target)) => target.to_string(),
+        (None, None) => context.system.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SpanNameContext, SpanNaming};
+
+    fn context<'a>(
+        operation: Option<&'a str>,
+        collection: Option<&'a str>,
+        namespace: Option<&'a str>,
+    ) -> SpanNameContext<'a> {
+        SpanNameContext {
+            method: "sqlx.fetch_one",
+            system: "postgresql",
+            operation,
+            collection,
+            namespace,
+        }
+    }
+
+    #[test]
+    fn should_follow_semantic_conventions() {
+        let naming = SpanNaming::default();
+        let name = |ctx| naming.name(&ctx).unwrap();
+        assert_eq!(
+            name(context(Some("SELECT"), Some("users"), Some("app"))),
+            "SELECT users"
+        );
+        assert_eq!(
+            name(context(Some("SELECT"), None, Some("app"))),
+            "SELECT app"
+        );
+        assert_eq!(name(context(Some("SELECT"), None, None)), "SELECT");
+        assert_eq!(name(context(None, None, Some("app"))), "app");
+        assert_eq!(name(context(None, None, None)), "postgresql");
+    }
+
+    #[test]
+    fn should_keep_method_or_use_custom_name() {
+        let ctx = context(Some("SELECT"), Some("users"), None);
+        assert_eq!(SpanNaming::Method.name(&ctx), None);
+        let naming = SpanNaming::custom(|ctx| format!("db:{}", ctx.method));
+        assert_eq!(naming.name(&ctx).as_deref(), Some("db:sqlx.fetch_one"));
+    }
+}
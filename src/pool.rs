@@ -1,112 +1,112 @@
-use futures::{StreamExt, TryStreamExt};
 use tracing::Instrument;
 
-impl<'p, DB> sqlx::Executor<'p> for &'_ crate::Pool<DB>
+/// Executor acquiring a connection of the pool for each statement, recording the time spent
+/// waiting for it. Wrapped in a [`Traced`](crate::Traced) executor to run the queries of the pool.
+#[cfg(feature = "metrics")]
+#[derive(Debug)]
+pub(crate) struct Acquiring<DB: sqlx::Database>(crate::Pool<DB>);
+
+impl<DB> crate::Pool<DB>
 where
-    DB: sqlx::Database + crate::prelude::Database,
-    for<'c> &'c mut DB::Connection: sqlx::Executor<'c, Database = DB>,
+    DB: crate::prelude::Database,
 {
-    type Database = DB;
-
-    #[doc(hidden)]
-    fn describe<'e, 'q: 'e>(
-        self,
-        sql: &'q str,
-    ) -> futures::future::BoxFuture<'e, Result<sqlx::Describe<Self::Database>, sqlx::Error>> {
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.describe", sql, attrs);
-        let fut = self.inner.describe(sql);
-        Box::pin(async move { fut.await.inspect_err(crate::span::record_error) }.instrument(span))
+    /// Creates the span of a connection acquisition, holding the pool state at request time.
+    fn acquire_span(&self) -> tracing::Span {
+        crate::span::dynamic_span!(
+            self.attributes
+                .lifecycle_level
+                .unwrap_or(tracing::Level::INFO),
+            "sqlx.acquire",
+            "db.client.connection.pool.name" = self.attributes.name.as_deref(),
+            "db.client.connection.pool.size" = self.inner.size(),
+            "db.client.connection.pool.idle" = self.inner.num_idle(),
+            "db.client.connection.wait_time" = tracing::field::Empty,
+            "db.client.connection.outcome" = tracing::field::Empty,
+            "error.type" = tracing::field::Empty,
+            "error.message" = tracing::field::Empty,
+            "error.stacktrace" = tracing::field::Empty,
+            "otel.status_code" = tracing::field::Empty,
+            "otel.status_description" = tracing::field::Empty,
+        )
     }
 
-    fn execute<'e, 'q: 'e, E>(
-        self,
-        query: E,
-    ) -> futures::future::BoxFuture<
-        'e,
-        Result<<Self::Database as sqlx::Database>::QueryResult, sqlx::Error>,
-    >
-    where
-        E: 'q + sqlx::Execute<'q, Self::Database>,
-    {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.execute", sql, attrs);
-        let fut = self.inner.execute(query);
-        Box::pin(async move { fut.await.inspect_err(crate::span::record_error) }.instrument(span))
+    /// Acquires a connection in a `sqlx.acquire` span, recording the wait time and outcome.
+    ///
+    /// When timing out, a snapshot of the pool state is attached to the span.
+    pub(crate) async fn acquire_traced(
+        &self,
+    ) -> Result<sqlx::pool::PoolConnection<DB>, sqlx::Error> {
+        let span = self.acquire_span();
+        let started = std::time::Instant::now();
+        let result = self.acquire_connection().instrument(span.clone()).await;
+        span.record(
+            "db.client.connection.wait_time",
+            started.elapsed().as_secs_f64(),
+        );
+        let outcome = match &result {
+            Ok(_) => "acquired",
+            Err(sqlx::Error::PoolTimedOut) => "timed_out",
+            Err(sqlx::Error::PoolClosed) => "closed",
+            Err(_) => "failed",
+        };
+        span.record("db.client.connection.outcome", outcome);
+        if let Err(err) = &result {
+            span.in_scope(|| crate::span::record_error::<DB>(err));
+        }
+        if matches!(result, Err(sqlx::Error::PoolTimedOut)) {
+            let options = self.inner.options();
+            tracing::warn!(
+                parent: &span,
+                db.client.connection.pool.size = self.inner.size(),
+                db.client.connection.pool.idle = self.inner.num_idle(),
+                db.client.connection.pool.max = options.get_max_connections(),
+                db.client.connection.pool.min = options.get_min_connections(),
+                db.client.connection.acquire_timeout = options.get_acquire_timeout().as_secs_f64(),
+                "timed out waiting for a connection",
+            );
+        }
+        result
     }
 
-    fn execute_many<'e, 'q: 'e, E>(
-        self,
-        query: E,
-    ) -> futures::stream::BoxStream<
-        'e,
-        Result<<Self::Database as sqlx::Database>::QueryResult, sqlx::Error>,
-    >
-    where
-        E: 'q + sqlx::Execute<'q, Self::Database>,
-    {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.execute_many", sql, attrs);
-        let stream = self.inner.execute_many(query);
-        use futures::StreamExt;
-        Box::pin(
-            stream
-                .inspect(move |_| {
-                    let _enter = span.enter();
-                })
-                .inspect_err(crate::span::record_error),
-        )
+    /// Traced executor running each statement on a connection acquired from the pool.
+    #[cfg(feature = "metrics")]
+    fn executor(&self) -> crate::Traced<Acquiring<DB>> {
+        self.traced(Acquiring(self.clone()))
     }
 
-    fn fetch<'e, 'q: 'e, E>(
-        self,
-        query: E,
-    ) -> futures::stream::BoxStream<'e, Result<<Self::Database as sqlx::Database>::Row, sqlx::Error>>
-    where
-        E: 'q + sqlx::Execute<'q, Self::Database>,
-    {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.fetch", sql, attrs);
-        let stream = self.inner.fetch(query);
-        use futures::StreamExt;
-        Box::pin(
-            stream
-                .inspect(move |_| {
-                    let _enter = span.enter();
-                })
-                .inspect_err(crate::span::record_error),
-        )
+    /// Traced executor running the statements on the SQLx pool, as nothing is recorded
+    /// about the connection acquisition without metrics.
+    #[cfg(not(feature = "metrics"))]
+    fn executor(&self) -> crate::Traced<&sqlx::Pool<DB>> {
+        self.traced(&self.inner)
     }
 
-    fn fetch_all<'e, 'q: 'e, E>(
-        self,
-        query: E,
-    ) -> futures::future::BoxFuture<
-        'e,
-        Result<Vec<<Self::Database as sqlx::Database>::Row>, sqlx::Error>,
-    >
-    where
-        E: 'q + sqlx::Execute<'q, Self::Database>,
-    {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.fetch_all", sql, attrs);
-        let fut = self.inner.fetch_all(query);
-        Box::pin(
-            async move {
-                fut.await
-                    .inspect(|res| {
-                        let span = tracing::Span::current();
-                        span.record("db.response.returned_rows", res.len());
-                    })
-                    .inspect_err(crate::span::record_error)
-            }
-            .instrument(span),
-        )
+    /// Attempts to acquire an idle connection in a `sqlx.acquire` span, without waiting.
+    pub(crate) fn try_acquire_traced(&self) -> Option<sqlx::pool::PoolConnection<DB>> {
+        let span = self.acquire_span();
+        let conn = span.in_scope(|| self.inner.try_acquire());
+        span.record("db.client.connection.wait_time", 0.0);
+        span.record(
+            "db.client.connection.outcome",
+            if conn.is_some() {
+                "acquired"
+            } else {
+                "unavailable"
+            },
+        );
+        conn
     }
+}
+
+/// Only the required methods are implemented, as for the SQLx pool: the others are derived from
+/// `fetch_many` and `fetch_optional`, so that a connection is acquired and timed in one place.
+#[cfg(feature = "metrics")]
+impl<'p, DB> sqlx::Executor<'p> for Acquiring<DB>
+where
+    DB: crate::prelude::Database,
+    for<'c> &'c mut DB::Connection: sqlx::Executor<'c, Database = DB>,
+{
+    type Database = DB;
 
     fn fetch_many<'e, 'q: 'e, E>(
         self,
@@ -124,38 +124,7 @@ where
     where
         E: 'q + sqlx::Execute<'q, Self::Database>,
     {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.fetch_all", sql, attrs);
-        let stream = self.inner.fetch_many(query);
-        Box::pin(
-            stream
-                .inspect(move |_| {
-                    let _enter = span.enter();
-                })
-                .inspect_err(crate::span::record_error),
-        )
-    }
-
-    fn fetch_one<'e, 'q: 'e, E>(
-        self,
-        query: E,
-    ) -> futures::future::BoxFuture<'e, Result<<Self::Database as sqlx::Database>::Row, sqlx::Error>>
-    where
-        E: 'q + sqlx::Execute<'q, Self::Database>,
-    {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.fetch_one", sql, attrs);
-        let fut = self.inner.fetch_one(query);
-        Box::pin(
-            async move {
-                fut.await
-                    .inspect(crate::span::record_one)
-                    .inspect_err(crate::span::record_error)
-            }
-            .instrument(span),
-        )
+        crate::stream::acquired::<DB, _>(self.0.acquire_connection(), query)
     }
 
     fn fetch_optional<'e, 'q: 'e, E>(
@@ -168,44 +137,40 @@ where
     where
         E: 'q + sqlx::Execute<'q, Self::Database>,
     {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.fetch_optional", sql, attrs);
-        let fut = self.inner.fetch_optional(query);
-        Box::pin(
-            async move {
-                fut.await
-                    .inspect(crate::span::record_optional)
-                    .inspect_err(crate::span::record_error)
-            }
-            .instrument(span),
-        )
+        let acquire = self.0.acquire_connection();
+        Box::pin(async move { acquire.await?.fetch_optional(query).await })
     }
 
-    fn prepare<'e, 'q: 'e>(
+    fn prepare_with<'e, 'q: 'e>(
         self,
-        query: &'q str,
+        sql: &'q str,
+        parameters: &'e [<Self::Database as sqlx::Database>::TypeInfo],
     ) -> futures::future::BoxFuture<
         'e,
         Result<<Self::Database as sqlx::Database>::Statement<'q>, sqlx::Error>,
     > {
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.prepare", query, attrs);
-        let fut = self.inner.prepare(query);
-        Box::pin(async move { fut.await.inspect_err(crate::span::record_error) }.instrument(span))
+        let acquire = self.0.acquire_connection();
+        Box::pin(async move { acquire.await?.prepare_with(sql, parameters).await })
     }
 
-    fn prepare_with<'e, 'q: 'e>(
+    #[doc(hidden)]
+    fn describe<'e, 'q: 'e>(
         self,
         sql: &'q str,
-        parameters: &'e [<Self::Database as sqlx::Database>::TypeInfo],
-    ) -> futures::future::BoxFuture<
-        'e,
-        Result<<Self::Database as sqlx::Database>::Statement<'q>, sqlx::Error>,
-    > {
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.prepare_with", sql, attrs);
-        let fut = self.inner.prepare_with(sql, parameters);
-        Box::pin(async move { fut.await.inspect_err(crate::span::record_error) }.instrument(span))
+    ) -> futures::future::BoxFuture<'e, Result<sqlx::Describe<Self::Database>, sqlx::Error>> {
+        let acquire = self.0.acquire_connection();
+        Box::pin(async move { acquire.await?.describe(sql).await })
     }
 }
+
+/// Implements `sqlx::Executor` for the pool, running the statements on a traced executor
+/// acquiring a connection for each of them.
+impl<'p, DB> sqlx::Executor<'p> for &'_ crate::Pool<DB>
+where
+    DB: crate::prelude::Database,
+    for<'c> &'c mut DB::Connection: sqlx::Executor<'c, Database = DB>,
+{
+    type Database = DB;
+
+    crate::traced::delegate_executor!('p, |this| this.executor());
+}
@@ -1,3 +1,38 @@
 impl crate::prelude::Database for sqlx::Postgres {
     const SYSTEM: &'static str = "postgresql";
+    const DIALECT: crate::prelude::Dialect = crate::prelude::Dialect::Postgres;
+
+    fn rows_affected(result: &sqlx::postgres::PgQueryResult) -> u64 {
+        result.rows_affected()
+    }
+
+    fn server(options: &sqlx::postgres::PgConnectOptions) -> crate::prelude::Server<'_> {
+        crate::prelude::Server {
+            host: Some(options.get_host()),
+            port: Some(options.get_port()),
+            database: options.get_database(),
+        }
+    }
+
+    /// The SQLSTATE code of the error.
+    fn status_code(error: &dyn sqlx::error::DatabaseError) -> Option<String> {
+        error
+            .try_downcast_ref::<sqlx::postgres::PgDatabaseError>()
+            .map(|error| error.code().to_owned())
+    }
+
+    fn record_error_details(span: &tracing::Span, error: &dyn sqlx::error::DatabaseError) {
+        let Some(error) = error.try_downcast_ref::<sqlx::postgres::PgDatabaseError>() else {
+            return;
+        };
+        span.record("db.postgresql.error.schema", error.schema());
+        span.record("db.postgresql.error.table", error.table());
+        span.record("db.postgresql.error.column", error.column());
+        span.record("db.postgresql.error.constraint", error.constraint());
+        span.record("db.postgresql.error.detail", error.detail());
+        span.record("db.postgresql.error.hint", error.hint());
+        if let Some(sqlx::postgres::PgErrorPosition::Original(position)) = error.position() {
+            span.record("db.postgresql.error.position", position);
+        }
+    }
 }
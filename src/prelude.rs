@@ -1,3 +1,32 @@
-pub trait Database {
+/// SQL dialect spoken by a database, used when sanitizing query text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    Postgres,
+    MySql,
+    Sqlite,
+}
+
+/// Location of a database, as read from the connect options of a driver.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Server<'a> {
+    pub host: Option<&'a str>,
+    pub port: Option<u16>,
+    pub database: Option<&'a str>,
+}
+
+pub trait Database: sqlx::Database {
     const SYSTEM: &'static str;
+    const DIALECT: Dialect;
+
+    /// Number of rows affected by a statement, as reported by the driver.
+    fn rows_affected(result: &<Self as sqlx::Database>::QueryResult) -> u64;
+
+    /// Location of the database the given connect options point to.
+    fn server(options: &<Self::Connection as sqlx::Connection>::Options) -> Server<'_>;
+
+    /// Status code of a database error, as recorded in `db.response.status_code`.
+    fn status_code(error: &dyn sqlx::error::DatabaseError) -> Option<String>;
+
+    /// Records the driver specific details of a database error in the given span.
+    fn record_error_details(span: &tracing::Span, error: &dyn sqlx::error::DatabaseError);
 }
@@ -0,0 +1,137 @@
+//! Query text sanitization, following the OpenTelemetry recommendations for `db.query.text`.
+//!
+//! String, numeric and blob literals are replaced by `?`, comments are removed and lists
+//! of literals or bind parameters following `IN` are collapsed into `IN (?)`. Bind
+//! parameters and identifiers are kept as they are.
+
+use crate::lexer::{Lexer, Token};
+use crate::prelude::Dialect;
+
+/// Replaces literals and comments from a SQL statement with placeholders.
+pub(crate) fn sanitize(sql: &str, dialect: Dialect) -> String {
+    let mut output = String::with_capacity(sql.len());
+    let mut lexer = Lexer::new(sql, dialect);
+    while let Some((token, text)) = lexer.next() {
+        match token {
+            Token::Comment => {
+                // avoid gluing the surrounding tokens together
+                if !output.is_empty() && !output.ends_with(|c: char| c.is_ascii_whitespace()) {
+                    output.push(' ');
+                }
+            }
+            Token::Literal => output.push('?'),
+            Token::Word if text.eq_ignore_ascii_case("in") => {
+                output.push_str(text);
+                let mut list = lexer.clone();
+                let blank = list.pos;
+                list.skip_blank();
+                let blank = &sql[blank..list.pos];
+                if let Some(end) = list.values_list() {
+                    if !blank.is_empty() {
+                        output.push(' ');
+                    }
+                    output.push_str("(?)");
+                    lexer = end;
+                }
+            }
+            _ => output.push_str(text),
+        }
+    }
+    output.truncate(output.trim_end().len());
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize;
+    use crate::prelude::Dialect;
+
+    #[test]
+    fn should_replace_literals() {
+        assert_eq!(
+            sanitize(
+                "SELECT * FROM users WHERE name = 'O''Brien' AND age > 42.5e3",
+                Dialect::Postgres
+            ),
+            "SELECT * FROM users WHERE name = ? AND age > ?"
+        );
+        assert_eq!(
+            sanitize("UPDATE t1 SET col2 = -0x1F, col3 = .5", Dialect::MySql),
+            "UPDATE t1 SET col2 = -?, col3 = ?"
+        );
+    }
+
+    #[test]
+    fn should_keep_parameters_and_identifiers() {
+        assert_eq!(
+            sanitize(
+                r#"SELECT "col 1", t2.c3 FROM t2 WHERE id = $1 AND x = $2::int"#,
+                Dialect::Postgres
+            ),
+            r#"SELECT "col 1", t2.c3 FROM t2 WHERE id = $1 AND x = $2::int"#
+        );
+        assert_eq!(
+            sanitize(
+                "SELECT [my col], `other` FROM t WHERE a = ?1 AND b = :name AND c = @v",
+                Dialect::Sqlite
+            ),
+            "SELECT [my col], `other` FROM t WHERE a = ?1 AND b = :name AND c = @v"
+        );
+    }
+
+    #[test]
+    fn should_remove_comments() {
+        assert_eq!(
+            sanitize(
+                "SELECT /* secret 'x' */ 1 -- trailing 'y'\nFROM t",
+                Dialect::Sqlite
+            ),
+            "SELECT  ? \nFROM t"
+        );
+        assert_eq!(
+            sanitize("SELECT 1 # hash comment", Dialect::MySql),
+            "SELECT ?"
+        );
+        assert_eq!(
+            sanitize("SELECT 1/* a /* nested */ b */", Dialect::Postgres),
+            "SELECT ?"
+        );
+    }
+
+    #[test]
+    fn should_collapse_in_lists() {
+        assert_eq!(
+            sanitize(
+                "SELECT * FROM t WHERE id IN (1, 2, 3) AND name in('a','b')",
+                Dialect::Postgres
+            ),
+            "SELECT * FROM t WHERE id IN (?) AND name in(?)"
+        );
+        assert_eq!(
+            sanitize(
+                "DELETE FROM t WHERE id IN ($1, $2) OR id IN (SELECT id FROM u WHERE x = 1)",
+                Dialect::Postgres
+            ),
+            "DELETE FROM t WHERE id IN (?) OR id IN (SELECT id FROM u WHERE x = ?)"
+        );
+    }
+
+    #[test]
+    fn should_handle_dialect_strings() {
+        assert_eq!(
+            sanitize(
+                r#"SELECT $$it's$$, $tag$a $$ b$tag$, E'it\'s', X'00ff' FROM t"#,
+                Dialect::Postgres
+            ),
+            "SELECT ?, ?, ?, ? FROM t"
+        );
+        assert_eq!(
+            sanitize(r#"SELECT "it\"s", 'a\'b' FROM t"#, Dialect::MySql),
+            "SELECT ?, ? FROM t"
+        );
+        assert_eq!(
+            sanitize(r#"SELECT 'a\', "b" FROM t"#, Dialect::Sqlite),
+            r#"SELECT ?, "b" FROM t"#
+        );
+    }
+}
@@ -0,0 +1,79 @@
+/// Environment variable used by OpenTelemetry instrumentations to opt in stable conventions.
+const STABILITY_OPT_IN: &str = "OTEL_SEMCONV_STABILITY_OPT_IN";
+
+/// Set of OpenTelemetry database semantic conventions emitted on spans.
+///
+/// `db.system.name` and `db.query.text` are emitted in every mode, in place of the legacy
+/// `db.system` and `db.statement`.
+///
+/// | Attribute      | Legacy          | Stable               |
+/// |----------------|-----------------|----------------------|
+/// | database       | `db.name`       | `db.namespace`       |
+/// | operation      | `db.operation`  | `db.operation.name`  |
+/// | table          | `db.sql.table`  | `db.collection.name` |
+/// | host           | `net.peer.name` | `server.address`     |
+/// | port           | `net.peer.port` | `server.port`        |
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SemconvStability {
+    /// Emits the attributes from the conventions prior to v1.26.
+    #[default]
+    Legacy,
+    /// Emits the attributes from the stable database conventions.
+    Stable,
+    /// Emits both the legacy and the stable attributes, to ease migrations.
+    Both,
+}
+
+impl SemconvStability {
+    /// Reads the mode from the `OTEL_SEMCONV_STABILITY_OPT_IN` environment variable.
+    ///
+    /// Following the OpenTelemetry migration guide, `database/dup` selects
+    /// [`SemconvStability::Both`], `database` selects [`SemconvStability::Stable`] and
+    /// any other value, or the variable not being set, selects
+    /// [`SemconvStability::Legacy`].
+    pub fn from_env() -> Self {
+        std::env::var(STABILITY_OPT_IN)
+            .map(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    fn parse(value: &str) -> Self {
+        let mut result = Self::Legacy;
+        for item in value.split(',').map(str::trim) {
+            match item {
+                "database/dup" => return Self::Both,
+                "database" => result = Self::Stable,
+                _ => {}
+            }
+        }
+        result
+    }
+
+    pub(crate) fn legacy(self) -> bool {
+        matches!(self, Self::Legacy | Self::Both)
+    }
+
+    pub(crate) fn stable(self) -> bool {
+        matches!(self, Self::Stable | Self::Both)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SemconvStability;
+
+    #[test]
+    fn should_parse_opt_in() {
+        assert_eq!(SemconvStability::parse(""), SemconvStability::default());
+        assert_eq!(SemconvStability::parse(""), SemconvStability::Legacy);
+        assert_eq!(SemconvStability::parse("http"), SemconvStability::Legacy);
+        assert_eq!(
+            SemconvStability::parse("http, database"),
+            SemconvStability::Stable
+        );
+        assert_eq!(
+            SemconvStability::parse("database,database/dup"),
+            SemconvStability::Both
+        );
+    }
+}
@@ -1,10 +1,42 @@
+/// Macro to create a tracing span with a level only known at runtime.
+///
+/// `tracing` requires the level of a callsite to be a constant, so a callsite is declared
+/// for each level and the one matching `$level` is used. Spans of disabled levels stay as
+/// cheap as with the `*_span!` macros. An explicit parent can be given with `parent:`.
+macro_rules! dynamic_span {
+    (@span [$($parent:tt)*] $level:expr, $name:expr, $($fields:tt)*) => {
+        match $level {
+            ::tracing::Level::ERROR => ::tracing::span!($($parent)* ::tracing::Level::ERROR, $name, $($fields)*),
+            ::tracing::Level::WARN => ::tracing::span!($($parent)* ::tracing::Level::WARN, $name, $($fields)*),
+            ::tracing::Level::INFO => ::tracing::span!($($parent)* ::tracing::Level::INFO, $name, $($fields)*),
+            ::tracing::Level::DEBUG => ::tracing::span!($($parent)* ::tracing::Level::DEBUG, $name, $($fields)*),
+            ::tracing::Level::TRACE => ::tracing::span!($($parent)* ::tracing::Level::TRACE, $name, $($fields)*),
+        }
+    };
+    // without a parent, the span stays contextual: an explicit `parent: None` would make it
+    // a root and detach it from the current OpenTelemetry context
+    (parent: $parent:expr, $level:expr, $name:expr, $($fields:tt)*) => {
+        match $parent {
+            Some(parent) => $crate::span::dynamic_span!(@span [parent: parent,] $level, $name, $($fields)*),
+            None => $crate::span::dynamic_span!(@span [] $level, $name, $($fields)*),
+        }
+    };
+    ($level:expr, $name:expr, $($fields:tt)*) => {
+        $crate::span::dynamic_span!(@span [] $level, $name, $($fields)*)
+    };
+}
+
+pub(crate) use dynamic_span;
+
 /// Macro to create a tracing span for a SQLx operation with OpenTelemetry-compatible fields.
 ///
 /// - `$name`: The operation name (e.g., "sqlx.execute").
 /// - `$statement`: The SQL statement being executed.
 /// - `$attributes`: Connection or pool attributes for peer and db context.
 ///
-/// This macro is used internally by the crate to instrument all major SQLx operations.
+/// The span carries the attribute set of the first releases. It is no longer used by the crate,
+/// whose executors record many more attributes, and is kept for compatibility.
+#[deprecated(note = "use the traced executors of the crate, which create the query spans")]
 #[macro_export]
 macro_rules! instrument {
     ($name:expr, $statement:expr, $attributes:expr) => {
@@ -43,6 +75,252 @@ macro_rules! instrument {
     };
 }
 
+/// Macro to create a tracing span for a SQLx operation with OpenTelemetry-compatible fields.
+///
+/// - `$name`: The operation name (e.g., "sqlx.execute").
+/// - `$statement`: The SQL statement being executed.
+/// - `$attributes`: Connection or pool attributes for peer and db context.
+///
+/// The span is created at the level configured on the pool, `INFO` by default, as a child
+/// of the current span or of the one given with `parent:`. It is returned along with the
+/// [`QueryTimer`] of the operation, sharing the statement parsed for the span.
+/// This macro is used internally by the crate to instrument all major SQLx operations.
+macro_rules! query_span {
+    (parent: $parent:expr, $name:expr, $statement:expr, $attributes:expr) => {
+        $crate::span::query_span!(@span [parent: $parent,] $name, $statement, $attributes)
+    };
+    (@span [$($parent:tt)*] $name:expr, $statement:expr, $attributes:expr) => {{
+        let span = $crate::span::dynamic_span!(
+            $($parent)*
+            $attributes.level.unwrap_or(::tracing::Level::INFO),
+            $name,
+            // Collection (table) name, parsed from the statement
+            "db.collection.name" = ::tracing::field::Empty,
+            // Database name (if available)
+            "db.namespace" = ::tracing::field::Empty,
+            // Operation type, parsed from the statement
+            "db.operation.name" = ::tracing::field::Empty,
+            // The SQL query text
+            "db.query.text" = ::tracing::field::Empty,
+            // Number of affected rows (to be filled after execution)
+            "db.response.affected_rows" = ::tracing::field::Empty,
+            // Number of returned rows (to be filled after execution)
+            "db.response.returned_rows" = ::tracing::field::Empty,
+            // Status code of the response (to be filled after execution)
+            "db.response.status_code" = ::tracing::field::Empty,
+            // Database system (e.g., "postgresql", "sqlite")
+            "db.system.name" = ::tracing::field::Empty,
+            // Server host and port
+            "server.address" = ::tracing::field::Empty,
+            "server.port" = ::tracing::field::Empty,
+            // Legacy conventions, emitted depending on the pool stability mode
+            "db.name" = ::tracing::field::Empty,
+            "db.operation" = ::tracing::field::Empty,
+            "db.sql.table" = ::tracing::field::Empty,
+            "net.peer.name" = ::tracing::field::Empty,
+            "net.peer.port" = ::tracing::field::Empty,
+            // Set when the query is slower than the pool threshold
+            "db.slow_query" = ::tracing::field::Empty,
+            // Error type, message, and stacktrace (to be filled on error)
+            "error.type" = ::tracing::field::Empty,
+            "error.message" = ::tracing::field::Empty,
+            "error.stacktrace" = ::tracing::field::Empty,
+            // Driver specific error details (to be filled on error)
+            "db.postgresql.error.schema" = ::tracing::field::Empty,
+            "db.postgresql.error.table" = ::tracing::field::Empty,
+            "db.postgresql.error.column" = ::tracing::field::Empty,
+            "db.postgresql.error.constraint" = ::tracing::field::Empty,
+            "db.postgresql.error.detail" = ::tracing::field::Empty,
+            "db.postgresql.error.hint" = ::tracing::field::Empty,
+            "db.postgresql.error.position" = ::tracing::field::Empty,
+            "db.mysql.error.number" = ::tracing::field::Empty,
+            "db.mysql.error.sql_state" = ::tracing::field::Empty,
+            // OpenTelemetry semantic fields
+            "otel.kind" = "client",
+            "otel.name" = ::tracing::field::Empty,
+            "otel.status_code" = ::tracing::field::Empty,
+            "otel.status_description" = ::tracing::field::Empty,
+            // Peer service name (if set)
+            "peer.service" = $attributes.name,
+        );
+        let statement = $crate::span::record_statement::<DB>(&span, $name, $statement, $attributes);
+        let timer = $crate::span::QueryTimer::<DB>::start($statement, $attributes, statement);
+        (span, timer)
+    }};
+    ($name:expr, $statement:expr, $attributes:expr) => {
+        $crate::span::query_span!(@span [] $name, $statement, $attributes)
+    };
+}
+
+pub(crate) use query_span;
+
+/// Returns the text to record as `db.query.text`, sanitized if enabled on the pool.
+fn query_text<'a, DB: crate::prelude::Database>(
+    sql: &'a str,
+    attributes: &crate::Attributes,
+) -> std::borrow::Cow<'a, str> {
+    if attributes.sanitize {
+        std::borrow::Cow::Owned(crate::sanitize::sanitize(sql, DB::DIALECT))
+    } else {
+        std::borrow::Cow::Borrowed(sql)
+    }
+}
+
+/// Records the connection attributes, the query text and the operation and collection
+/// parsed from the statement in the given span, following the pool stability mode, and
+/// names it according to the pool naming strategy.
+/// Nothing is computed when the span is disabled, otherwise the parsed statement is returned.
+pub(crate) fn record_statement<'a, DB: crate::prelude::Database>(
+    span: &tracing::Span,
+    method: &'static str,
+    sql: &'a str,
+    attributes: &crate::Attributes,
+) -> Option<crate::inspect::Statement<'a>> {
+    if span.is_disabled() {
+        return None;
+    }
+    let stability = attributes.stability();
+    let text = query_text::<DB>(sql, attributes);
+    let statement = crate::inspect::inspect(sql, DB::DIALECT);
+    // recorded in every mode, as they were before the stable conventions were supported, so
+    // their legacy counterparts `db.system` and `db.statement` are never recorded
+    span.record("db.system.name", DB::SYSTEM);
+    span.record("db.query.text", text.as_ref());
+    record_location(span, attributes);
+    if stability.stable() {
+        span.record("db.operation.name", statement.operation.as_deref());
+        span.record("db.collection.name", statement.collection);
+    }
+    if stability.legacy() {
+        span.record("db.operation", statement.operation.as_deref());
+        span.record("db.sql.table", statement.collection);
+    }
+    let context = crate::SpanNameContext {
+        method,
+        system: DB::SYSTEM,
+        operation: statement.operation.as_deref(),
+        collection: statement.collection,
+        namespace: attributes.database.as_deref(),
+    };
+    if let Some(name) = attributes.naming.name(&context) {
+        span.record("otel.name", name.as_str());
+    }
+    Some(statement)
+}
+
+/// Records the database name, host and port in the given span, under the attribute names of
+/// the stability mode of the pool.
+pub(crate) fn record_location(span: &tracing::Span, attributes: &crate::Attributes) {
+    let stability = attributes.stability();
+    if stability.stable() {
+        span.record("db.namespace", attributes.database.as_deref());
+        span.record("server.address", attributes.host.as_deref());
+        span.record("server.port", attributes.port);
+    }
+    if stability.legacy() {
+        span.record("db.name", attributes.database.as_deref());
+        span.record("net.peer.name", attributes.host.as_deref());
+        span.record("net.peer.port", attributes.port);
+    }
+}
+
+/// Status code of a database error, as reported by the driver.
+fn status_code<DB: crate::prelude::Database>(err: &sqlx::Error) -> Option<String> {
+    match err {
+        sqlx::Error::Database(inner) => DB::status_code(inner.as_ref()),
+        _ => None,
+    }
+}
+
+/// Measures the duration of a query, to report it when slower than the pool threshold
+/// and to record it in the `db.client.operation.duration` metric.
+///
+/// The clock only starts when the duration is needed by the pool.
+pub(crate) struct QueryTimer<'q, DB> {
+    started: Option<(
+        std::time::Instant,
+        std::sync::Arc<crate::Attributes>,
+        crate::inspect::Statement<'q>,
+    )>,
+    sql: &'q str,
+    error_type: Option<String>,
+    database: std::marker::PhantomData<fn() -> DB>,
+}
+
+impl<'q, DB: crate::prelude::Database> QueryTimer<'q, DB> {
+    /// Starts the clock, reusing the statement parsed for the span when it is enabled.
+    pub(crate) fn start(
+        sql: &'q str,
+        attributes: &std::sync::Arc<crate::Attributes>,
+        statement: Option<crate::inspect::Statement<'q>>,
+    ) -> Self {
+        Self {
+            started: attributes.is_timed().then(|| {
+                let statement =
+                    statement.unwrap_or_else(|| crate::inspect::inspect(sql, DB::DIALECT));
+                (std::time::Instant::now(), attributes.clone(), statement)
+            }),
+            sql,
+            error_type: None,
+            database: std::marker::PhantomData,
+        }
+    }
+
+    /// Awaits the given future and reports the query in the current span.
+    pub(crate) async fn watch<F, T>(mut self, fut: F) -> Result<T, sqlx::Error>
+    where
+        F: std::future::Future<Output = Result<T, sqlx::Error>>,
+    {
+        let output = fut.await;
+        if let Err(err) = &output {
+            self.fail(err);
+        }
+        self.finish(&tracing::Span::current());
+        output
+    }
+
+    /// Keeps the type of the error the query failed with.
+    pub(crate) fn fail(&mut self, err: &sqlx::Error) {
+        if self.started.is_some() {
+            let status_code = status_code::<DB>(err);
+            let error_type = crate::error::error_type(err, status_code.as_deref());
+            self.error_type = Some(error_type.to_string());
+        }
+    }
+
+    /// Records the duration of the query and, when it exceeded the threshold, marks the
+    /// span with `db.slow_query` and emits a warning event in it.
+    pub(crate) fn finish(&mut self, span: &tracing::Span) {
+        let Some((started, attributes, statement)) = self.started.take() else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = attributes.metrics.as_ref() {
+            metrics.record_operation::<DB>(
+                elapsed,
+                &attributes,
+                &statement,
+                self.error_type.as_deref(),
+            );
+        }
+        if attributes
+            .slow_query_threshold
+            .is_some_and(|threshold| elapsed > threshold)
+        {
+            span.record("db.slow_query", true);
+            span.in_scope(|| {
+                tracing::warn!(
+                    elapsed_ms = elapsed.as_secs_f64() * 1000.0,
+                    "db.operation.name" = statement.operation.as_deref(),
+                    "db.query.text" = crate::sanitize::sanitize(self.sql, DB::DIALECT),
+                    "slow query",
+                )
+            });
+        }
+    }
+}
+
 /// Records that a single row was returned in the current tracing span.
 /// Used for fetch_one operations.
 pub fn record_one<T>(_value: &T) {
@@ -60,29 +338,65 @@ pub fn record_optional<T>(value: &Option<T>) {
     );
 }
 
+/// Records the number of rows affected by a statement in the current tracing span.
+/// Used for execute operations.
+pub fn record_execute<DB: crate::prelude::Database>(result: &DB::QueryResult) {
+    let span = tracing::Span::current();
+    span.record("db.response.affected_rows", DB::rows_affected(result));
+}
+
 /// Records error details in the current tracing span for a SQLx error.
 /// Sets OpenTelemetry status and error fields for observability backends.
-pub fn record_error(err: &sqlx::Error) {
+///
+/// For database errors, the driver status code is recorded in `db.response.status_code`
+/// and the driver specific details (constraint, table...) in their own attributes.
+/// The error is classified in `error.type`, see [`crate::error`] for the mapping.
+pub fn record_error<DB: crate::prelude::Database>(err: &sqlx::Error) {
     let span = tracing::Span::current();
     // Mark the span as an error for OpenTelemetry
     span.record("otel.status_code", "error");
     span.record("otel.status_description", err.to_string());
-    // Classify error type as client or server
-    match err {
-        sqlx::Error::ColumnIndexOutOfBounds { .. }
-        | sqlx::Error::ColumnDecode { .. }
-        | sqlx::Error::ColumnNotFound(_)
-        | sqlx::Error::Decode { .. }
-        | sqlx::Error::Encode { .. }
-        | sqlx::Error::RowNotFound
-        | sqlx::Error::TypeNotFound { .. } => {
-            span.record("error.type", "client");
-        }
-        _ => {
-            span.record("error.type", "server");
-        }
+    if let sqlx::Error::Database(inner) = err {
+        DB::record_error_details(&span, inner.as_ref());
+    }
+    let status_code = status_code::<DB>(err);
+    if let Some(code) = status_code.as_deref() {
+        span.record("db.response.status_code", code);
     }
+    span.record(
+        "error.type",
+        crate::error::error_type(err, status_code.as_deref()),
+    );
     // Attach error message and stacktrace for debugging
     span.record("error.message", err.to_string());
     span.record("error.stacktrace", format!("{err:?}"));
 }
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn should_skip_disabled_spans() {
+        let named = Arc::new(AtomicBool::new(false));
+        let attributes = crate::Attributes {
+            naming: crate::SpanNaming::custom({
+                let named = named.clone();
+                move |_| {
+                    named.store(true, Ordering::Relaxed);
+                    String::new()
+                }
+            }),
+            ..Default::default()
+        };
+        let statement = super::record_statement::<sqlx::Sqlite>(
+            &tracing::Span::none(),
+            "sqlx.execute",
+            "select * from users",
+            &attributes,
+        );
+        assert!(statement.is_none());
+        assert!(!named.load(Ordering::Relaxed));
+    }
+}
@@ -1,3 +1,23 @@
 impl crate::prelude::Database for sqlx::Sqlite {
     const SYSTEM: &'static str = "sqlite";
+    const DIALECT: crate::prelude::Dialect = crate::prelude::Dialect::Sqlite;
+
+    fn rows_affected(result: &sqlx::sqlite::SqliteQueryResult) -> u64 {
+        result.rows_affected()
+    }
+
+    fn server(options: &sqlx::sqlite::SqliteConnectOptions) -> crate::prelude::Server<'_> {
+        crate::prelude::Server {
+            host: options.get_filename().to_str(),
+            ..Default::default()
+        }
+    }
+
+    /// The extended result code of the error.
+    fn status_code(error: &dyn sqlx::error::DatabaseError) -> Option<String> {
+        error.code().map(|code| code.into_owned())
+    }
+
+    /// SQLite errors only carry a code and a message, already recorded.
+    fn record_error_details(_span: &tracing::Span, _error: &dyn sqlx::error::DatabaseError) {}
 }
@@ -0,0 +1,172 @@
+//! Standalone connections, opened outside of a pool, as used by command line tools,
+//! migration runners or tests.
+
+use std::sync::Arc;
+
+use sqlx::{ConnectOptions, Connection};
+use tracing::Instrument;
+
+/// Creates the span of a lifecycle operation on a connection, like `sqlx.connect`, at the
+/// lifecycle level.
+macro_rules! lifecycle_span {
+    ($name:expr, $attributes:expr) => {{
+        let span = crate::span::dynamic_span!(
+            $attributes.lifecycle_level.unwrap_or(tracing::Level::INFO),
+            $name,
+            "db.system.name" = DB::SYSTEM,
+            "db.namespace" = tracing::field::Empty,
+            "server.address" = tracing::field::Empty,
+            "server.port" = tracing::field::Empty,
+            "db.name" = tracing::field::Empty,
+            "net.peer.name" = tracing::field::Empty,
+            "net.peer.port" = tracing::field::Empty,
+            "error.type" = tracing::field::Empty,
+            "error.message" = tracing::field::Empty,
+            "error.stacktrace" = tracing::field::Empty,
+            "otel.status_code" = tracing::field::Empty,
+            "otel.status_description" = tracing::field::Empty,
+            "peer.service" = $attributes.name.as_deref(),
+        );
+        crate::span::record_location(&span, &$attributes);
+        span
+    }};
+}
+
+pub(crate) use lifecycle_span;
+
+/// Runs a lifecycle operation in the given span, recording its error.
+pub(crate) async fn lifecycle<DB, F, T>(span: tracing::Span, fut: F) -> Result<T, sqlx::Error>
+where
+    DB: crate::prelude::Database,
+    F: Future<Output = Result<T, sqlx::Error>>,
+{
+    async move { fut.await.inspect_err(crate::span::record_error::<DB>) }
+        .instrument(span)
+        .await
+}
+
+impl<DB: sqlx::Database> std::fmt::Debug for crate::TracedConnection<DB> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TracedConnection").finish_non_exhaustive()
+    }
+}
+
+impl<DB> crate::TracedConnection<DB>
+where
+    DB: crate::prelude::Database,
+{
+    fn with_attributes(inner: DB::Connection, mut attributes: crate::Attributes) -> Self {
+        attributes
+            .stability
+            .get_or_insert_with(crate::SemconvStability::from_env);
+        #[cfg(feature = "metrics")]
+        {
+            let meter = crate::metrics::Metrics::global_meter();
+            attributes.metrics = Some(crate::metrics::Metrics::operations(&meter));
+        }
+        Self {
+            inner: crate::Traced {
+                inner: Box::new(inner),
+                attributes: Arc::new(attributes),
+                transaction: None,
+            },
+        }
+    }
+
+    /// Wraps an existing connection.
+    ///
+    /// The location of the database can't be read from a connection, so the spans only carry
+    /// the database system.
+    pub fn new(inner: DB::Connection) -> Self {
+        Self::with_attributes(inner, crate::Attributes::default())
+    }
+
+    /// Opens a connection to the given URL.
+    ///
+    /// See [`Self::connect_with`] for the tracing of the connect attempt.
+    pub async fn connect(url: &str) -> Result<Self, sqlx::Error> {
+        let options = url.parse()?;
+        Self::connect_with(&options).await
+    }
+
+    /// Opens a connection with the given options, in a `sqlx.connect` span.
+    ///
+    /// The attributes are read from the connect options.
+    pub async fn connect_with(
+        options: &<DB::Connection as Connection>::Options,
+    ) -> Result<Self, sqlx::Error> {
+        let attributes = crate::Attributes::from_options::<DB>(options);
+        let span = lifecycle_span!("sqlx.connect", attributes);
+        let inner = lifecycle::<DB, _, _>(span, options.connect()).await?;
+        Ok(Self::with_attributes(inner, attributes))
+    }
+
+    /// Begins a new transaction.
+    ///
+    /// The returned [`Transaction`](crate::Transaction) is instrumented for tracing, like with
+    /// [`Pool::begin`](crate::Pool::begin).
+    #[track_caller]
+    pub fn begin(
+        &mut self,
+    ) -> impl Future<Output = Result<crate::Transaction<'_, DB>, sqlx::Error>> + use<'_, DB> {
+        let location = std::panic::Location::caller();
+        async move {
+            let crate::Traced {
+                inner, attributes, ..
+            } = &mut self.inner;
+            crate::transaction::TransactionSpan::new::<DB>(attributes, location)
+                .begin(Ok(&mut **inner), attributes)
+                .await
+        }
+    }
+
+    /// Checks the connection is still alive, in a `sqlx.ping` span.
+    pub async fn ping(&mut self) -> Result<(), sqlx::Error> {
+        let span = lifecycle_span!("sqlx.ping", self.inner.attributes);
+        lifecycle::<DB, _, _>(span, self.inner.inner.ping()).await
+    }
+
+    /// Gracefully closes the connection, in a `sqlx.close` span.
+    pub async fn close(self) -> Result<(), sqlx::Error> {
+        let crate::Traced {
+            inner, attributes, ..
+        } = self.inner;
+        let span = lifecycle_span!("sqlx.close", attributes);
+        lifecycle::<DB, _, _>(span, inner.close()).await
+    }
+}
+
+impl<DB> AsMut<<DB as sqlx::Database>::Connection> for crate::TracedConnection<DB>
+where
+    DB: crate::prelude::Database,
+{
+    fn as_mut(&mut self) -> &mut <DB as sqlx::Database>::Connection {
+        &mut self.inner.inner
+    }
+}
+
+impl<DB: sqlx::Database> std::ops::Deref for crate::TracedConnection<DB> {
+    type Target = crate::Traced<Box<DB::Connection>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<DB: sqlx::Database> std::ops::DerefMut for crate::TracedConnection<DB> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+/// Implements `sqlx::Executor` for a mutable reference to a tracing-instrumented standalone
+/// connection, running the statements on the traced connection it dereferences to.
+impl<'c, DB> sqlx::Executor<'c> for &'c mut crate::TracedConnection<DB>
+where
+    DB: crate::prelude::Database + sqlx::Database,
+    for<'a> &'a mut DB::Connection: sqlx::Executor<'a, Database = DB>,
+{
+    type Database = DB;
+
+    crate::traced::delegate_executor!('c, |this| (&mut this.inner));
+}
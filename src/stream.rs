@@ -0,0 +1,196 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use futures::stream::BoxStream;
+#[cfg(feature = "metrics")]
+use futures::{SinkExt, StreamExt};
+
+/// Item yielded by `fetch_many`.
+type Many<DB> = sqlx::Either<<DB as sqlx::Database>::QueryResult, <DB as sqlx::Database>::Row>;
+
+/// Row and result counters accumulated while a stream is being consumed.
+#[derive(Debug, Default)]
+struct Counts {
+    returned_rows: Option<u64>,
+    affected_rows: Option<u64>,
+}
+
+/// Stream wrapper keeping the query span open until the stream is exhausted or dropped.
+///
+/// The inner stream is polled inside the span, items are counted as they flow and
+/// errors are recorded on the span owning the query.
+pub(crate) struct TracedStream<'e, DB: crate::prelude::Database, T> {
+    inner: BoxStream<'e, Result<T, sqlx::Error>>,
+    span: tracing::Span,
+    timer: crate::span::QueryTimer<'e, DB>,
+    counts: Counts,
+    count: fn(&mut Counts, &T),
+    finished: bool,
+}
+
+impl<'e, DB: crate::prelude::Database, T> TracedStream<'e, DB, T> {
+    /// Records the final counters and the duration of the query, and closes the span.
+    fn finish(&mut self) {
+        self.timer.finish(&self.span);
+        if let Some(value) = self.counts.returned_rows {
+            self.span.record("db.response.returned_rows", value);
+        }
+        if let Some(value) = self.counts.affected_rows {
+            self.span.record("db.response.affected_rows", value);
+        }
+        // dropping our handle closes the span
+        self.span = tracing::Span::none();
+        self.finished = true;
+    }
+}
+
+impl<'e, DB: crate::prelude::Database, T> Stream for TracedStream<'e, DB, T> {
+    type Item = Result<T, sqlx::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.finished {
+            return this.inner.as_mut().poll_next(cx);
+        }
+        let enter = this.span.enter();
+        let next = this.inner.as_mut().poll_next(cx);
+        match &next {
+            Poll::Ready(Some(Ok(item))) => (this.count)(&mut this.counts, item),
+            Poll::Ready(Some(Err(err))) => {
+                crate::span::record_error::<DB>(err);
+                this.timer.fail(err);
+            }
+            Poll::Ready(None) => {
+                drop(enter);
+                this.finish();
+            }
+            Poll::Pending => {}
+        }
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'e, DB: crate::prelude::Database, T> Drop for TracedStream<'e, DB, T> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.finish();
+        }
+    }
+}
+
+/// Wraps a stream of rows, as returned by `fetch`.
+pub(crate) fn rows<'e, DB>(
+    inner: BoxStream<'e, Result<DB::Row, sqlx::Error>>,
+    span: tracing::Span,
+    timer: crate::span::QueryTimer<'e, DB>,
+) -> TracedStream<'e, DB, DB::Row>
+where
+    DB: crate::prelude::Database,
+{
+    TracedStream {
+        inner,
+        span,
+        timer,
+        counts: Counts {
+            returned_rows: Some(0),
+            affected_rows: None,
+        },
+        count: |counts, _| {
+            *counts.returned_rows.get_or_insert(0) += 1;
+        },
+        finished: false,
+    }
+}
+
+/// Wraps a stream of query results, as returned by `execute_many`.
+pub(crate) fn results<'e, DB>(
+    inner: BoxStream<'e, Result<DB::QueryResult, sqlx::Error>>,
+    span: tracing::Span,
+    timer: crate::span::QueryTimer<'e, DB>,
+) -> TracedStream<'e, DB, DB::QueryResult>
+where
+    DB: crate::prelude::Database,
+{
+    TracedStream {
+        inner,
+        span,
+        timer,
+        counts: Counts {
+            returned_rows: None,
+            affected_rows: Some(0),
+        },
+        count: |counts, result| {
+            *counts.affected_rows.get_or_insert(0) += DB::rows_affected(result);
+        },
+        finished: false,
+    }
+}
+
+/// Wraps a stream mixing query results and rows, as returned by `fetch_many`.
+pub(crate) fn many<'e, DB>(
+    inner: BoxStream<'e, Result<Many<DB>, sqlx::Error>>,
+    span: tracing::Span,
+    timer: crate::span::QueryTimer<'e, DB>,
+) -> TracedStream<'e, DB, Many<DB>>
+where
+    DB: crate::prelude::Database,
+{
+    TracedStream {
+        inner,
+        span,
+        timer,
+        counts: Counts {
+            returned_rows: Some(0),
+            affected_rows: Some(0),
+        },
+        count: |counts, item| match item {
+            sqlx::Either::Left(result) => {
+                *counts.affected_rows.get_or_insert(0) += DB::rows_affected(result);
+            }
+            sqlx::Either::Right(_) => {
+                *counts.returned_rows.get_or_insert(0) += 1;
+            }
+        },
+        finished: false,
+    }
+}
+
+/// Runs `fetch_many` on a connection acquired from a pool, as `&Pool` executors do.
+///
+/// The stream borrows the connection, so both are owned by a future forwarding the
+/// items through a channel, polled along with it.
+#[cfg(feature = "metrics")]
+pub(crate) fn acquired<'e, 'q: 'e, DB, E>(
+    acquire: impl Future<Output = Result<sqlx::pool::PoolConnection<DB>, sqlx::Error>> + Send + 'e,
+    query: E,
+) -> BoxStream<'e, Result<Many<DB>, sqlx::Error>>
+where
+    DB: sqlx::Database,
+    E: 'q + sqlx::Execute<'q, DB>,
+    for<'c> &'c mut DB::Connection: sqlx::Executor<'c, Database = DB>,
+{
+    let (mut sender, receiver) = futures::channel::mpsc::channel(0);
+    let forward = async move {
+        let mut conn = match acquire.await {
+            Ok(conn) => conn,
+            Err(err) => {
+                let _ = sender.send(Err(err)).await;
+                return;
+            }
+        };
+        let mut stream = sqlx::Executor::fetch_many(&mut *conn, query);
+        while let Some(item) = stream.next().await {
+            if sender.send(item).await.is_err() {
+                break;
+            }
+        }
+    };
+    // the future yields no item, the stream ends once it is done and the channel drained
+    let forward = futures::stream::once(forward).filter_map(|()| std::future::ready(None));
+    futures::stream::select(receiver, forward).boxed()
+}
@@ -0,0 +1,546 @@
+use tracing::Instrument;
+
+/// Implements the methods of `sqlx::Executor` by delegating them to the executor built by the
+/// given expression from `self`, named `$this` in it, so that the executors of the crate share
+/// the instrumentation of [`Traced`](crate::Traced).
+macro_rules! delegate_executor {
+    ($lifetime:lifetime, |$this:ident| $executor:expr) => {
+        #[doc(hidden)]
+        fn describe<'e, 'q: 'e>(
+            self,
+            sql: &'q str,
+        ) -> futures::future::BoxFuture<'e, Result<sqlx::Describe<Self::Database>, sqlx::Error>>
+        where
+            $lifetime: 'e,
+        {
+            let $this = self;
+            $executor.describe(sql)
+        }
+
+        fn execute<'e, 'q: 'e, E>(
+            self,
+            query: E,
+        ) -> futures::future::BoxFuture<
+            'e,
+            Result<<Self::Database as sqlx::Database>::QueryResult, sqlx::Error>,
+        >
+        where
+            E: 'q + sqlx::Execute<'q, Self::Database>,
+            $lifetime: 'e,
+        {
+            let $this = self;
+            $executor.execute(query)
+        }
+
+        fn execute_many<'e, 'q: 'e, E>(
+            self,
+            query: E,
+        ) -> futures::stream::BoxStream<
+            'e,
+            Result<<Self::Database as sqlx::Database>::QueryResult, sqlx::Error>,
+        >
+        where
+            E: 'q + sqlx::Execute<'q, Self::Database>,
+            $lifetime: 'e,
+        {
+            let $this = self;
+            $executor.execute_many(query)
+        }
+
+        fn fetch<'e, 'q: 'e, E>(
+            self,
+            query: E,
+        ) -> futures::stream::BoxStream<
+            'e,
+            Result<<Self::Database as sqlx::Database>::Row, sqlx::Error>,
+        >
+        where
+            E: 'q + sqlx::Execute<'q, Self::Database>,
+            $lifetime: 'e,
+        {
+            let $this = self;
+            $executor.fetch(query)
+        }
+
+        fn fetch_all<'e, 'q: 'e, E>(
+            self,
+            query: E,
+        ) -> futures::future::BoxFuture<
+            'e,
+            Result<Vec<<Self::Database as sqlx::Database>::Row>, sqlx::Error>,
+        >
+        where
+            E: 'q + sqlx::Execute<'q, Self::Database>,
+            $lifetime: 'e,
+        {
+            let $this = self;
+            $executor.fetch_all(query)
+        }
+
+        fn fetch_many<'e, 'q: 'e, E>(
+            self,
+            query: E,
+        ) -> futures::stream::BoxStream<
+            'e,
+            Result<
+                sqlx::Either<
+                    <Self::Database as sqlx::Database>::QueryResult,
+                    <Self::Database as sqlx::Database>::Row,
+                >,
+                sqlx::Error,
+            >,
+        >
+        where
+            E: 'q + sqlx::Execute<'q, Self::Database>,
+            $lifetime: 'e,
+        {
+            let $this = self;
+            $executor.fetch_many(query)
+        }
+
+        fn fetch_one<'e, 'q: 'e, E>(
+            self,
+            query: E,
+        ) -> futures::future::BoxFuture<
+            'e,
+            Result<<Self::Database as sqlx::Database>::Row, sqlx::Error>,
+        >
+        where
+            E: 'q + sqlx::Execute<'q, Self::Database>,
+            $lifetime: 'e,
+        {
+            let $this = self;
+            $executor.fetch_one(query)
+        }
+
+        fn fetch_optional<'e, 'q: 'e, E>(
+            self,
+            query: E,
+        ) -> futures::future::BoxFuture<
+            'e,
+            Result<Option<<Self::Database as sqlx::Database>::Row>, sqlx::Error>,
+        >
+        where
+            E: 'q + sqlx::Execute<'q, Self::Database>,
+            $lifetime: 'e,
+        {
+            let $this = self;
+            $executor.fetch_optional(query)
+        }
+
+        fn prepare<'e, 'q: 'e>(
+            self,
+            query: &'q str,
+        ) -> futures::future::BoxFuture<
+            'e,
+            Result<<Self::Database as sqlx::Database>::Statement<'q>, sqlx::Error>,
+        >
+        where
+            $lifetime: 'e,
+        {
+            let $this = self;
+            $executor.prepare(query)
+        }
+
+        fn prepare_with<'e, 'q: 'e>(
+            self,
+            sql: &'q str,
+            parameters: &'e [<Self::Database as sqlx::Database>::TypeInfo],
+        ) -> futures::future::BoxFuture<
+            'e,
+            Result<<Self::Database as sqlx::Database>::Statement<'q>, sqlx::Error>,
+        >
+        where
+            $lifetime: 'e,
+        {
+            let $this = self;
+            $executor.prepare_with(sql, parameters)
+        }
+    };
+}
+
+pub(crate) use delegate_executor;
+
+impl<E> crate::Traced<E> {
+    /// Wraps an executor, to instrument the queries it runs.
+    ///
+    /// The spans only carry the database system, use [`Pool::traced`](crate::Pool::traced) to
+    /// share the attributes of a pool.
+    /// With the `metrics` feature, the duration of the operations is recorded with the global
+    /// meter provider.
+    pub fn new(inner: E) -> Self {
+        let attributes = crate::Attributes {
+            stability: Some(crate::SemconvStability::from_env()),
+            #[cfg(feature = "metrics")]
+            metrics: Some(crate::metrics::Metrics::operations(
+                &crate::metrics::Metrics::global_meter(),
+            )),
+            ..Default::default()
+        };
+        Self {
+            inner,
+            attributes: std::sync::Arc::new(attributes),
+            transaction: None,
+        }
+    }
+
+    /// Unwraps the executor, whose queries are not traced.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    /// Set a custom name for the executor (for peer.service attribute).
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        std::sync::Arc::make_mut(&mut self.attributes).name = Some(name.into());
+        self
+    }
+
+    /// Set the database name attribute.
+    pub fn with_database(mut self, database: impl Into<String>) -> Self {
+        std::sync::Arc::make_mut(&mut self.attributes).database = Some(database.into());
+        self
+    }
+
+    /// Set the host attribute.
+    pub fn with_host(mut self, host: impl Into<String>) -> Self {
+        std::sync::Arc::make_mut(&mut self.attributes).host = Some(host.into());
+        self
+    }
+
+    /// Set the port attribute.
+    pub fn with_port(mut self, port: u16) -> Self {
+        std::sync::Arc::make_mut(&mut self.attributes).port = Some(port);
+        self
+    }
+
+    /// Set the level of the query spans, `INFO` by default.
+    pub fn with_level(mut self, level: tracing::Level) -> Self {
+        std::sync::Arc::make_mut(&mut self.attributes).level = Some(level);
+        self
+    }
+
+    /// Sanitize the query text before recording it in `db.query.text`, as with
+    /// [`PoolBuilder::with_query_sanitization`](crate::PoolBuilder::with_query_sanitization).
+    pub fn with_query_sanitization(mut self, enabled: bool) -> Self {
+        std::sync::Arc::make_mut(&mut self.attributes).sanitize = enabled;
+        self
+    }
+
+    /// Set the strategy used to name query spans.
+    pub fn with_span_naming(mut self, naming: crate::SpanNaming) -> Self {
+        std::sync::Arc::make_mut(&mut self.attributes).naming = naming;
+        self
+    }
+
+    /// Select which semantic conventions are emitted on spans, instead of the mode read from
+    /// the environment.
+    pub fn with_semconv_stability(mut self, stability: crate::SemconvStability) -> Self {
+        std::sync::Arc::make_mut(&mut self.attributes).stability = Some(stability);
+        self
+    }
+
+    /// Report the queries taking longer than the given threshold, as with
+    /// [`PoolBuilder::with_slow_query_threshold`](crate::PoolBuilder::with_slow_query_threshold).
+    pub fn with_slow_query_threshold(mut self, threshold: std::time::Duration) -> Self {
+        std::sync::Arc::make_mut(&mut self.attributes).slow_query_threshold = Some(threshold);
+        self
+    }
+
+    /// Set the meter recording the duration of the operations, instead of the global one.
+    #[cfg(feature = "metrics")]
+    pub fn with_meter(mut self, meter: &opentelemetry::metrics::Meter) -> Self {
+        std::sync::Arc::make_mut(&mut self.attributes).metrics =
+            Some(crate::metrics::Metrics::operations(meter));
+        self
+    }
+
+    /// Counts a statement run in the enclosing transaction, if any, and returns the parent
+    /// of its span.
+    fn statement(&self) -> Option<tracing::Id> {
+        match &self.transaction {
+            Some(transaction) => transaction.statement(),
+            None => tracing::Span::current().id(),
+        }
+    }
+}
+
+impl<C: std::ops::DerefMut> crate::Traced<C> {
+    /// Traced executor borrowing the connection, sharing the attributes and the transaction.
+    pub(crate) fn reborrow(&mut self) -> crate::Traced<&mut C::Target> {
+        crate::Traced {
+            inner: &mut *self.inner,
+            attributes: self.attributes.clone(),
+            transaction: self.transaction.clone(),
+        }
+    }
+}
+
+impl<C> std::fmt::Debug for crate::Traced<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Traced").finish_non_exhaustive()
+    }
+}
+
+impl<C: std::ops::Deref> std::ops::Deref for crate::Traced<C> {
+    type Target = C::Target;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<C: std::ops::DerefMut> std::ops::DerefMut for crate::Traced<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+/// Implements `sqlx::Executor` for any tracing-instrumented executor.
+///
+/// Each method creates a tracing span for the SQL operation, attaches relevant attributes,
+/// and records errors or row counts as appropriate for observability. This is the single
+/// place the queries are instrumented, the other executors of the crate delegate to it.
+impl<'c, X, DB> sqlx::Executor<'c> for crate::Traced<X>
+where
+    X: sqlx::Executor<'c, Database = DB>,
+    DB: crate::prelude::Database,
+{
+    type Database = DB;
+
+    #[doc(hidden)]
+    fn describe<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+    ) -> futures::future::BoxFuture<'e, Result<sqlx::Describe<Self::Database>, sqlx::Error>>
+    where
+        'c: 'e,
+    {
+        let attrs = &self.attributes;
+        let parent = self.statement();
+        let (span, timer) = crate::span::query_span!(parent: parent, "sqlx.describe", sql, attrs);
+        let fut = timer.watch(self.inner.describe(sql));
+        Box::pin(
+            async move { fut.await.inspect_err(crate::span::record_error::<DB>) }.instrument(span),
+        )
+    }
+
+    fn execute<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> futures::future::BoxFuture<
+        'e,
+        Result<<Self::Database as sqlx::Database>::QueryResult, sqlx::Error>,
+    >
+    where
+        E: 'q + sqlx::Execute<'q, Self::Database>,
+        'c: 'e,
+    {
+        let sql = query.sql();
+        let attrs = &self.attributes;
+        let parent = self.statement();
+        let (span, timer) = crate::span::query_span!(parent: parent, "sqlx.execute", sql, attrs);
+        let fut = timer.watch(self.inner.execute(query));
+        Box::pin(
+            async move {
+                fut.await
+                    .inspect(crate::span::record_execute::<DB>)
+                    .inspect_err(crate::span::record_error::<DB>)
+            }
+            .instrument(span),
+        )
+    }
+
+    fn execute_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> futures::stream::BoxStream<
+        'e,
+        Result<<Self::Database as sqlx::Database>::QueryResult, sqlx::Error>,
+    >
+    where
+        E: 'q + sqlx::Execute<'q, Self::Database>,
+        'c: 'e,
+    {
+        let sql = query.sql();
+        let attrs = &self.attributes;
+        let parent = self.statement();
+        let (span, timer) =
+            crate::span::query_span!(parent: parent, "sqlx.execute_many", sql, attrs);
+        let stream = self.inner.execute_many(query);
+        Box::pin(crate::stream::results::<DB>(stream, span, timer))
+    }
+
+    fn fetch<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> futures::stream::BoxStream<'e, Result<<Self::Database as sqlx::Database>::Row, sqlx::Error>>
+    where
+        E: 'q + sqlx::Execute<'q, Self::Database>,
+        'c: 'e,
+    {
+        let sql = query.sql();
+        let attrs = &self.attributes;
+        let parent = self.statement();
+        let (span, timer) = crate::span::query_span!(parent: parent, "sqlx.fetch", sql, attrs);
+        let stream = self.inner.fetch(query);
+        Box::pin(crate::stream::rows::<DB>(stream, span, timer))
+    }
+
+    fn fetch_all<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> futures::future::BoxFuture<
+        'e,
+        Result<Vec<<Self::Database as sqlx::Database>::Row>, sqlx::Error>,
+    >
+    where
+        E: 'q + sqlx::Execute<'q, Self::Database>,
+        'c: 'e,
+    {
+        let sql = query.sql();
+        let attrs = &self.attributes;
+        let parent = self.statement();
+        let (span, timer) = crate::span::query_span!(parent: parent, "sqlx.fetch_all", sql, attrs);
+        let fut = timer.watch(self.inner.fetch_all(query));
+        Box::pin(
+            async move {
+                fut.await
+                    .inspect(|res| {
+                        let span = tracing::Span::current();
+                        span.record("db.response.returned_rows", res.len());
+                    })
+                    .inspect_err(crate::span::record_error::<DB>)
+            }
+            .instrument(span),
+        )
+    }
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> futures::stream::BoxStream<
+        'e,
+        Result<
+            sqlx::Either<
+                <Self::Database as sqlx::Database>::QueryResult,
+                <Self::Database as sqlx::Database>::Row,
+            >,
+            sqlx::Error,
+        >,
+    >
+    where
+        E: 'q + sqlx::Execute<'q, Self::Database>,
+        'c: 'e,
+    {
+        let sql = query.sql();
+        let attrs = &self.attributes;
+        let parent = self.statement();
+        let (span, timer) = crate::span::query_span!(parent: parent, "sqlx.fetch_many", sql, attrs);
+        let stream = self.inner.fetch_many(query);
+        Box::pin(crate::stream::many::<DB>(stream, span, timer))
+    }
+
+    fn fetch_one<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> futures::future::BoxFuture<'e, Result<<Self::Database as sqlx::Database>::Row, sqlx::Error>>
+    where
+        E: 'q + sqlx::Execute<'q, Self::Database>,
+        'c: 'e,
+    {
+        let sql = query.sql();
+        let attrs = &self.attributes;
+        let parent = self.statement();
+        let (span, timer) = crate::span::query_span!(parent: parent, "sqlx.fetch_one", sql, attrs);
+        let fut = timer.watch(self.inner.fetch_one(query));
+        Box::pin(
+            async move {
+                fut.await
+                    .inspect(crate::span::record_one)
+                    .inspect_err(crate::span::record_error::<DB>)
+            }
+            .instrument(span),
+        )
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> futures::future::BoxFuture<
+        'e,
+        Result<Option<<Self::Database as sqlx::Database>::Row>, sqlx::Error>,
+    >
+    where
+        E: 'q + sqlx::Execute<'q, Self::Database>,
+        'c: 'e,
+    {
+        let sql = query.sql();
+        let attrs = &self.attributes;
+        let parent = self.statement();
+        let (span, timer) =
+            crate::span::query_span!(parent: parent, "sqlx.fetch_optional", sql, attrs);
+        let fut = timer.watch(self.inner.fetch_optional(query));
+        Box::pin(
+            async move {
+                fut.await
+                    .inspect(crate::span::record_optional)
+                    .inspect_err(crate::span::record_error::<DB>)
+            }
+            .instrument(span),
+        )
+    }
+
+    fn prepare<'e, 'q: 'e>(
+        self,
+        query: &'q str,
+    ) -> futures::future::BoxFuture<
+        'e,
+        Result<<Self::Database as sqlx::Database>::Statement<'q>, sqlx::Error>,
+    >
+    where
+        'c: 'e,
+    {
+        let attrs = &self.attributes;
+        let parent = self.statement();
+        let (span, timer) = crate::span::query_span!(parent: parent, "sqlx.prepare", query, attrs);
+        let fut = timer.watch(self.inner.prepare(query));
+        Box::pin(
+            async move { fut.await.inspect_err(crate::span::record_error::<DB>) }.instrument(span),
+        )
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [<Self::Database as sqlx::Database>::TypeInfo],
+    ) -> futures::future::BoxFuture<
+        'e,
+        Result<<Self::Database as sqlx::Database>::Statement<'q>, sqlx::Error>,
+    >
+    where
+        'c: 'e,
+    {
+        let attrs = &self.attributes;
+        let parent = self.statement();
+        let (span, timer) =
+            crate::span::query_span!(parent: parent, "sqlx.prepare_with", sql, attrs);
+        let fut = timer.watch(self.inner.prepare_with(sql, parameters));
+        Box::pin(
+            async move { fut.await.inspect_err(crate::span::record_error::<DB>) }.instrument(span),
+        )
+    }
+}
+
+/// Implements `sqlx::Executor` for a mutable reference to a tracing-instrumented connection,
+/// running the statements on a traced executor borrowing it.
+impl<'c, C, Conn, DB> sqlx::Executor<'c> for &'c mut crate::Traced<C>
+where
+    C: std::ops::DerefMut<Target = Conn> + Send,
+    Conn: sqlx::Connection<Database = DB> + 'c,
+    DB: crate::prelude::Database,
+    for<'a> &'a mut Conn: sqlx::Executor<'a, Database = DB>,
+{
+    type Database = DB;
+
+    delegate_executor!('c, |this| this.reborrow());
+}
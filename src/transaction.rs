@@ -1,263 +1,344 @@
-use futures::{StreamExt, TryStreamExt};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use sqlx::Error;
 use tracing::Instrument;
 
-impl<'c, DB> crate::Transaction<'c, DB>
-where
-    DB: crate::prelude::Database + sqlx::Database,
-    for<'a> &'a mut DB::Connection: sqlx::Executor<'a, Database = DB>,
-{
-    /// Returns a tracing-instrumented executor for this transaction.
-    ///
-    /// This allows running queries with full span context and attributes.
-    pub fn executor(&mut self) -> crate::Connection<'_, DB> {
-        crate::Connection {
-            inner: &mut *self.inner,
-            attributes: self.attributes.clone(),
+/// Creates a `db.transaction` or `db.savepoint` span, at the lifecycle level of the pool.
+macro_rules! transaction_span {
+    (parent: $parent:expr, $name:expr, $depth:expr, $attributes:expr) => {{
+        let span = crate::span::dynamic_span!(
+            parent: $parent,
+            $attributes.lifecycle_level.unwrap_or(tracing::Level::INFO),
+            $name,
+            "db.system.name" = DB::SYSTEM,
+            "db.namespace" = tracing::field::Empty,
+            "server.address" = tracing::field::Empty,
+            "server.port" = tracing::field::Empty,
+            "db.name" = tracing::field::Empty,
+            "net.peer.name" = tracing::field::Empty,
+            "net.peer.port" = tracing::field::Empty,
+            "db.transaction.depth" = $depth,
+            "db.transaction.outcome" = tracing::field::Empty,
+            "db.transaction.duration" = tracing::field::Empty,
+            "db.transaction.statements" = tracing::field::Empty,
+            "error.type" = tracing::field::Empty,
+            "error.message" = tracing::field::Empty,
+            "error.stacktrace" = tracing::field::Empty,
+            "otel.status_code" = tracing::field::Empty,
+            "otel.status_description" = tracing::field::Empty,
+            "peer.service" = $attributes.name.as_deref(),
+        );
+        crate::span::record_location(&span, $attributes);
+        span
+    }};
+}
+
+/// Parent of the spans created in a transaction, the current span when the transaction span
+/// is disabled so that they don't become roots.
+fn parent(span: &tracing::Span) -> Option<tracing::Id> {
+    span.id().or_else(|| tracing::Span::current().id())
+}
+
+/// Handle on the span of a transaction, held by the executors running statements in it.
+#[derive(Clone, Debug)]
+pub(crate) struct TransactionScope {
+    span: tracing::Span,
+    statements: Arc<AtomicU64>,
+}
+
+impl TransactionScope {
+    /// Counts a statement run in the transaction and returns the parent of its span.
+    pub(crate) fn statement(&self) -> Option<tracing::Id> {
+        self.statements.fetch_add(1, Ordering::Relaxed);
+        parent(&self.span)
+    }
+}
+
+/// Span covering a transaction, from `BEGIN` until it is committed, rolled back or dropped,
+/// or a savepoint nested in it, at a depth greater than 1.
+///
+/// Statements run in the transaction are counted and their spans are created as children
+/// of this one. The outcome, duration and statement count are recorded when it is dropped.
+/// A transaction dropped without commit or rollback is reported with the location it was
+/// created at, once `BEGIN` succeeded.
+#[derive(Debug)]
+pub(crate) struct TransactionSpan {
+    span: tracing::Span,
+    depth: usize,
+    location: &'static std::panic::Location<'static>,
+    started: std::time::Instant,
+    statements: Arc<AtomicU64>,
+    outcome: Option<&'static str>,
+    begun: bool,
+}
+
+impl TransactionSpan {
+    fn with_span(
+        span: tracing::Span,
+        depth: usize,
+        location: &'static std::panic::Location<'static>,
+    ) -> Self {
+        Self {
+            span,
+            depth,
+            location,
+            started: std::time::Instant::now(),
+            statements: Arc::default(),
+            outcome: None,
+            begun: false,
         }
     }
 
-    /// Commits this transaction or savepoint.
-    pub async fn commit(self) -> Result<(), Error> {
-        self.inner.commit().await
+    pub(crate) fn new<DB: crate::prelude::Database>(
+        attributes: &crate::Attributes,
+        location: &'static std::panic::Location<'static>,
+    ) -> Self {
+        let span = transaction_span!(
+            parent: tracing::Span::current().id(),
+            "db.transaction",
+            1,
+            attributes
+        );
+        Self::with_span(span, 1, location)
     }
 
-    /// Aborts this transaction or savepoint.
-    pub async fn rollback(self) -> Result<(), Error> {
-        self.inner.rollback().await
+    /// Creates the span of a savepoint nested in this transaction.
+    fn savepoint<DB: crate::prelude::Database>(
+        &self,
+        attributes: &crate::Attributes,
+        location: &'static std::panic::Location<'static>,
+    ) -> Self {
+        let depth = self.depth + 1;
+        let span = transaction_span!(parent: self.parent(), "db.savepoint", depth, attributes);
+        Self::with_span(span, depth, location)
     }
-}
 
-/// Implements `sqlx::Executor` for a mutable reference to a tracing-instrumented transaction.
-///
-/// Each method creates a tracing span for the SQL operation, attaches relevant attributes,
-/// and records errors or row counts as appropriate for observability.
-impl<'c, DB> sqlx::Executor<'c> for &'c mut crate::Transaction<'c, DB>
-where
-    DB: crate::prelude::Database + sqlx::Database,
-    for<'a> &'a mut DB::Connection: sqlx::Executor<'a, Database = DB>,
-{
-    type Database = DB;
+    /// Statement run by SQLx at this depth, the savepoints being named after the depth of
+    /// the transaction they are created in.
+    fn sql(&self, transaction: &'static str, savepoint: &str) -> std::borrow::Cow<'static, str> {
+        match self.depth {
+            1 => std::borrow::Cow::Borrowed(transaction),
+            depth => std::borrow::Cow::Owned(format!("{savepoint} _sqlx_savepoint_{}", depth - 1)),
+        }
+    }
 
-    #[doc(hidden)]
-    fn describe<'e, 'q: 'e>(
-        self,
-        sql: &'q str,
-    ) -> futures::future::BoxFuture<'e, Result<sqlx::Describe<Self::Database>, sqlx::Error>>
-    where
-        'c: 'e,
-    {
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.describe", sql, attrs);
-        Box::pin(
-            async move {
-                let fut = (&mut self.inner).describe(sql);
-                fut.await.inspect_err(crate::span::record_error)
-            }
-            .instrument(span),
-        )
+    /// Statement run by SQLx to begin a savepoint nested in this transaction.
+    pub(crate) fn savepoint_sql(&self) -> String {
+        format!("SAVEPOINT _sqlx_savepoint_{}", self.depth)
     }
 
-    fn execute<'e, 'q: 'e, E>(
-        self,
-        query: E,
-    ) -> futures::future::BoxFuture<
-        'e,
-        Result<<Self::Database as sqlx::Database>::QueryResult, sqlx::Error>,
-    >
-    where
-        E: 'q + sqlx::Execute<'q, Self::Database>,
-        'c: 'e,
-    {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.execute", sql, attrs);
-        let fut = (&mut self.inner).execute(query);
-        Box::pin(async move { fut.await.inspect_err(crate::span::record_error) }.instrument(span))
+    pub(crate) fn span(&self) -> &tracing::Span {
+        &self.span
     }
 
-    fn execute_many<'e, 'q: 'e, E>(
-        self,
-        query: E,
-    ) -> futures::stream::BoxStream<
-        'e,
-        Result<<Self::Database as sqlx::Database>::QueryResult, sqlx::Error>,
-    >
-    where
-        E: 'q + sqlx::Execute<'q, Self::Database>,
-        'c: 'e,
-    {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.execute_many", sql, attrs);
-        let stream = (&mut self.inner).execute_many(query);
-        use futures::StreamExt;
-        Box::pin(
-            stream
-                .inspect(move |_| {
-                    let _enter = span.enter();
-                })
-                .inspect_err(crate::span::record_error),
-        )
+    /// Parent of the spans created in the transaction.
+    pub(crate) fn parent(&self) -> Option<tracing::Id> {
+        parent(&self.span)
     }
 
-    fn fetch<'e, 'q: 'e, E>(
-        self,
-        query: E,
-    ) -> futures::stream::BoxStream<'e, Result<<Self::Database as sqlx::Database>::Row, sqlx::Error>>
-    where
-        E: 'q + sqlx::Execute<'q, Self::Database>,
-        'c: 'e,
-    {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.fetch", sql, attrs);
-        let stream = (&mut self.inner).fetch(query);
-        use futures::StreamExt;
-        Box::pin(
-            stream
-                .inspect(move |_| {
-                    let _enter = span.enter();
-                })
-                .inspect_err(crate::span::record_error),
-        )
+    /// Handle given to the executors running statements in the transaction.
+    pub(crate) fn scope(&self) -> TransactionScope {
+        TransactionScope {
+            span: self.span.clone(),
+            statements: self.statements.clone(),
+        }
     }
 
-    fn fetch_all<'e, 'q: 'e, E>(
-        self,
-        query: E,
-    ) -> futures::future::BoxFuture<
-        'e,
-        Result<Vec<<Self::Database as sqlx::Database>::Row>, sqlx::Error>,
-    >
-    where
-        E: 'q + sqlx::Execute<'q, Self::Database>,
-        'c: 'e,
-    {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.fetch_all", sql, attrs);
-        let fut = (&mut self.inner).fetch_all(query);
-        Box::pin(
-            async move {
-                fut.await
-                    .inspect(|res| {
-                        let span = tracing::Span::current();
-                        span.record("db.response.returned_rows", res.len());
-                    })
-                    .inspect_err(crate::span::record_error)
-            }
-            .instrument(span),
-        )
+    /// Marks the transaction as failed, when it could not begin, commit or roll back.
+    fn fail<DB: crate::prelude::Database>(&mut self, err: &Error) {
+        self.outcome = Some("failed");
+        self.span.in_scope(|| crate::span::record_error::<DB>(err));
     }
 
-    fn fetch_many<'e, 'q: 'e, E>(
-        self,
-        query: E,
-    ) -> futures::stream::BoxStream<
-        'e,
-        Result<
-            sqlx::Either<
-                <Self::Database as sqlx::Database>::QueryResult,
-                <Self::Database as sqlx::Database>::Row,
-            >,
-            sqlx::Error,
-        >,
-    >
-    where
-        E: 'q + sqlx::Execute<'q, Self::Database>,
-        'c: 'e,
-    {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.fetch_all", sql, attrs);
-        let stream = (&mut self.inner).fetch_many(query);
-        Box::pin(
-            stream
-                .inspect(move |_| {
-                    let _enter = span.enter();
-                })
-                .inspect_err(crate::span::record_error),
-        )
+    /// Ends the span of a transaction which could not begin, for lack of an idle connection.
+    pub(crate) fn unavailable(mut self) {
+        self.outcome = Some("unavailable");
     }
 
-    fn fetch_one<'e, 'q: 'e, E>(
-        self,
-        query: E,
-    ) -> futures::future::BoxFuture<'e, Result<<Self::Database as sqlx::Database>::Row, sqlx::Error>>
+    /// Runs `BEGIN`, `COMMIT` or `ROLLBACK`, or their savepoint counterparts, in the given
+    /// timed span, child of the transaction.
+    async fn control<DB, F, T>(
+        &mut self,
+        (span, timer): (tracing::Span, crate::span::QueryTimer<'_, DB>),
+        fut: F,
+    ) -> Result<T, Error>
     where
-        E: 'q + sqlx::Execute<'q, Self::Database>,
-        'c: 'e,
+        DB: crate::prelude::Database,
+        F: Future<Output = Result<T, Error>>,
     {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.fetch_one", sql, attrs);
-        let fut = (&mut self.inner).fetch_one(query);
-        Box::pin(
-            async move {
-                fut.await
-                    .inspect(crate::span::record_one)
-                    .inspect_err(crate::span::record_error)
-            }
-            .instrument(span),
-        )
+        let result = async move {
+            timer
+                .watch(fut)
+                .await
+                .inspect_err(crate::span::record_error::<DB>)
+        }
+        .instrument(span)
+        .await;
+        if let Err(err) = &result {
+            self.fail::<DB>(err);
+        }
+        result
     }
 
-    fn fetch_optional<'e, 'q: 'e, E>(
-        self,
-        query: E,
-    ) -> futures::future::BoxFuture<
-        'e,
-        Result<Option<<Self::Database as sqlx::Database>::Row>, sqlx::Error>,
-    >
+    /// Begins a transaction, or a savepoint, on the connection, in a `sqlx.begin` span.
+    pub(crate) async fn begin<'c, DB>(
+        mut self,
+        conn: Result<impl Into<sqlx::pool::MaybePoolConnection<'c, DB>>, Error>,
+        attributes: &Arc<crate::Attributes>,
+    ) -> Result<crate::Transaction<'c, DB>, Error>
     where
-        E: 'q + sqlx::Execute<'q, Self::Database>,
-        'c: 'e,
+        DB: crate::prelude::Database,
     {
-        let sql = query.sql();
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.fetch_optional", sql, attrs);
-        let fut = (&mut self.inner).fetch_optional(query);
-        Box::pin(
-            async move {
-                fut.await
-                    .inspect(crate::span::record_optional)
-                    .inspect_err(crate::span::record_error)
+        let conn = conn.inspect_err(|err| self.fail::<DB>(err))?;
+        let sql = self.sql("BEGIN", "SAVEPOINT");
+        let instrumented =
+            crate::span::query_span!(parent: self.parent(), "sqlx.begin", &sql, attributes);
+        let inner = self
+            .control(instrumented, sqlx::Transaction::begin(conn, None))
+            .await?;
+        self.begun = true;
+        Ok(crate::Transaction {
+            inner: crate::Traced {
+                inner,
+                attributes: attributes.clone(),
+                transaction: Some(self.scope()),
+            },
+            span: self,
+        })
+    }
+}
+
+impl Drop for TransactionSpan {
+    fn drop(&mut self) {
+        let outcome = match self.outcome {
+            Some(outcome) => outcome,
+            // the future beginning the transaction was dropped before BEGIN completed
+            None if !self.begun => "cancelled",
+            None => {
+                // sqlx queues a rollback, which usually hides an early return
+                self.span.in_scope(|| {
+                    tracing::warn!(
+                        code.file.path = self.location.file(),
+                        code.line.number = self.location.line(),
+                        code.column.number = self.location.column(),
+                        "transaction dropped without commit or rollback",
+                    )
+                });
+                "implicit_rollback"
             }
-            .instrument(span),
-        )
+        };
+        self.span.record("db.transaction.outcome", outcome);
+        self.span.record(
+            "db.transaction.duration",
+            self.started.elapsed().as_secs_f64(),
+        );
+        self.span.record(
+            "db.transaction.statements",
+            self.statements.load(Ordering::Relaxed),
+        );
     }
+}
 
-    fn prepare<'e, 'q: 'e>(
-        self,
-        query: &'q str,
-    ) -> futures::future::BoxFuture<
-        'e,
-        Result<<Self::Database as sqlx::Database>::Statement<'q>, sqlx::Error>,
-    >
-    where
-        'c: 'e,
-    {
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.prepare", query, attrs);
-        let fut = (&mut self.inner).prepare(query);
-        Box::pin(async move { fut.await.inspect_err(crate::span::record_error) }.instrument(span))
+impl<'c, DB: sqlx::Database> std::ops::Deref for crate::Transaction<'c, DB> {
+    type Target = crate::Traced<sqlx::Transaction<'c, DB>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
     }
+}
 
-    fn prepare_with<'e, 'q: 'e>(
-        self,
-        sql: &'q str,
-        parameters: &'e [<Self::Database as sqlx::Database>::TypeInfo],
-    ) -> futures::future::BoxFuture<
-        'e,
-        Result<<Self::Database as sqlx::Database>::Statement<'q>, sqlx::Error>,
-    >
-    where
-        'c: 'e,
-    {
-        let attrs = &self.attributes;
-        let span = crate::instrument!("sqlx.prepare_with", sql, attrs);
-        let fut = (&mut self.inner).prepare_with(sql, parameters);
-        Box::pin(async move { fut.await.inspect_err(crate::span::record_error) }.instrument(span))
+impl<'c, DB: sqlx::Database> std::ops::DerefMut for crate::Transaction<'c, DB> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<'c, DB> crate::Transaction<'c, DB>
+where
+    DB: crate::prelude::Database + sqlx::Database,
+    for<'a> &'a mut DB::Connection: sqlx::Executor<'a, Database = DB>,
+{
+    /// Returns a tracing-instrumented executor for this transaction.
+    ///
+    /// This allows running queries with full span context and attributes, their spans
+    /// being children of the `db.transaction` span.
+    pub fn executor(&mut self) -> crate::Connection<'_, DB> {
+        crate::Connection {
+            inner: self.inner.reborrow(),
+        }
+    }
+
+    /// Begins a savepoint nested in this transaction.
+    ///
+    /// The returned [`Transaction`](crate::Transaction) is instrumented for tracing, within a
+    /// `db.savepoint` span child of this one, carrying its depth in `db.transaction.depth`.
+    #[track_caller]
+    pub fn begin<'t>(
+        &'t mut self,
+    ) -> impl Future<Output = Result<crate::Transaction<'t, DB>, Error>> + use<'t, 'c, DB> {
+        let location = std::panic::Location::caller();
+        async move {
+            let crate::Traced {
+                inner, attributes, ..
+            } = &mut self.inner;
+            let span = self.span.savepoint::<DB>(attributes, location);
+            span.begin(Ok(&mut **inner), attributes).await
+        }
+    }
+
+    /// Commits this transaction or releases this savepoint, in a `sqlx.commit` span.
+    pub async fn commit(self) -> Result<(), Error> {
+        let Self {
+            inner: crate::Traced {
+                inner, attributes, ..
+            },
+            span: mut transaction,
+        } = self;
+        let sql = transaction.sql("COMMIT", "RELEASE SAVEPOINT");
+        let instrumented = crate::span::query_span!(
+            parent: transaction.parent(),
+            "sqlx.commit",
+            &sql,
+            &attributes
+        );
+        transaction.control(instrumented, inner.commit()).await?;
+        transaction.outcome = Some(match transaction.depth {
+            1 => "committed",
+            _ => "released",
+        });
+        Ok(())
     }
+
+    /// Aborts this transaction or savepoint, in a `sqlx.rollback` span.
+    pub async fn rollback(self) -> Result<(), Error> {
+        let Self {
+            inner: crate::Traced {
+                inner, attributes, ..
+            },
+            span: mut transaction,
+        } = self;
+        let sql = transaction.sql("ROLLBACK", "ROLLBACK TO SAVEPOINT");
+        let instrumented = crate::span::query_span!(
+            parent: transaction.parent(),
+            "sqlx.rollback",
+            &sql,
+            &attributes
+        );
+        transaction.control(instrumented, inner.rollback()).await?;
+        transaction.outcome = Some("rolled_back");
+        Ok(())
+    }
+}
+
+/// Implements `sqlx::Executor` for a mutable reference to a tracing-instrumented transaction,
+/// running the statements on the traced connection it dereferences to.
+impl<'c, 't, DB> sqlx::Executor<'c> for &'c mut crate::Transaction<'t, DB>
+where
+    DB: crate::prelude::Database + sqlx::Database,
+    for<'a> &'a mut DB::Connection: sqlx::Executor<'a, Database = DB>,
+{
+    type Database = DB;
+
+    crate::traced::delegate_executor!('c, |this| (&mut this.inner));
 }
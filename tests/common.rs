@@ -1,31 +1,24 @@
 use std::time::Duration;
 
+use futures::TryStreamExt;
 use opentelemetry::trace::{FutureExt, TraceContextExt, Tracer};
+use opentelemetry_testing::{RootTrace, Span};
 
-pub async fn should_trace<'c, DB, E>(
+/// Runs `future` in a root span of the `should_{name}_{system}` scope, then returns its output
+/// with the exported traces and the id of the root span.
+pub async fn traced<F: Future>(
     name: &'static str,
     system: &'static str,
     observability: &opentelemetry_testing::ObservabilityContainer,
     provider: &opentelemetry_testing::OpenTelemetryProvider,
-    executor: E,
-) where
-    DB: sqlx::Database,
-    E: sqlx::Executor<'c, Database = DB>,
-    for<'q> DB::Arguments<'q>: 'q + sqlx::IntoArguments<'q, DB>,
-    (i32,): Send + Unpin + for<'r> sqlx::FromRow<'r, DB::Row>,
-{
+    future: F,
+) -> (F::Output, RootTrace, String) {
     let scope = format!("should_{name}_{system}");
     let tracer = opentelemetry::global::tracer(scope.clone());
     let span = tracer.span_builder(name).start(&tracer);
     let ctx = opentelemetry::Context::new().with_span(span);
 
-    let result: Option<i32> = sqlx::query_scalar("select 1")
-        .fetch_optional(executor)
-        .with_context(ctx)
-        .await
-        .unwrap();
-
-    assert_eq!(result, Some(1));
+    let output = future.with_context(ctx).await;
 
     provider.flush();
 
@@ -35,13 +28,215 @@ pub async fn should_trace<'c, DB, E>(
     let scope_span = traces.find_scope_span(&scope).unwrap();
     let entry = scope_span.first_span().unwrap();
     assert_eq!(entry.name, name);
-    let next = traces
-        .find_child(&entry.span_id, "sqlx.fetch_optional")
-        .unwrap();
+    let root = entry.span_id.clone();
+    (output, traces, root)
+}
+
+/// Spans created under the given parent span.
+fn children<'a>(traces: &'a RootTrace, parent: &'a str) -> impl Iterator<Item = &'a Span> {
+    traces
+        .resource_spans
+        .iter()
+        .flat_map(|resource| &resource.scope_spans)
+        .flat_map(|scope| &scope.spans)
+        .filter(move |span| span.parent_span_id.as_deref() == Some(parent))
+}
+
+/// Finds the span of the given query, run under the given root span.
+fn find_query<'a>(traces: &'a RootTrace, root: &'a str, sql: &str) -> Option<&'a Span> {
+    children(traces, root).find(|span| span.string_attribute("db.query.text") == Some(sql))
+}
+
+pub async fn should_trace<'c, DB, E>(
+    name: &'static str,
+    system: &'static str,
+    span_name: &str,
+    observability: &opentelemetry_testing::ObservabilityContainer,
+    provider: &opentelemetry_testing::OpenTelemetryProvider,
+    executor: E,
+) where
+    DB: sqlx::Database,
+    E: sqlx::Executor<'c, Database = DB>,
+    for<'q> DB::Arguments<'q>: 'q + sqlx::IntoArguments<'q, DB>,
+    (i32,): Send + Unpin + for<'r> sqlx::FromRow<'r, DB::Row>,
+{
+    let (result, traces, root) = traced(
+        name,
+        system,
+        observability,
+        provider,
+        sqlx::query_scalar("select 1").fetch_optional(executor),
+    )
+    .await;
+
+    let result: Option<i32> = result.unwrap();
+    assert_eq!(result, Some(1));
+
+    let next = traces.find_child(&root, span_name).unwrap();
     assert_eq!(next.string_attribute("db.system.name").unwrap(), system);
     assert_eq!(next.string_attribute("db.query.text").unwrap(), "select 1");
+    assert_eq!(next.string_attribute("db.operation").unwrap(), "SELECT");
     assert_eq!(
         next.int_attribute("db.response.returned_rows").unwrap(),
         "1"
     );
 }
+
+pub async fn should_record_affected_rows<'c, DB, E>(
+    system: &'static str,
+    observability: &opentelemetry_testing::ObservabilityContainer,
+    provider: &opentelemetry_testing::OpenTelemetryProvider,
+    executor: E,
+) where
+    DB: sqlx::Database,
+    E: sqlx::Executor<'c, Database = DB> + Copy,
+    for<'q> DB::Arguments<'q>: 'q + sqlx::IntoArguments<'q, DB>,
+{
+    sqlx::query("create table affected (id integer)")
+        .execute(executor)
+        .await
+        .unwrap();
+
+    let (result, traces, root) = traced(
+        "affected_rows",
+        system,
+        observability,
+        provider,
+        sqlx::query("insert into affected (id) values (1), (2)").execute(executor),
+    )
+    .await;
+
+    result.unwrap();
+    let next = traces.find_child(&root, "INSERT affected").unwrap();
+    assert_eq!(
+        next.int_attribute("db.response.affected_rows").unwrap(),
+        "2"
+    );
+}
+
+pub async fn should_trace_stream<'c, DB, E>(
+    system: &'static str,
+    observability: &opentelemetry_testing::ObservabilityContainer,
+    provider: &opentelemetry_testing::OpenTelemetryProvider,
+    executor: E,
+) where
+    DB: sqlx::Database,
+    E: sqlx::Executor<'c, Database = DB> + 'c,
+    for<'q> DB::Arguments<'q>: 'q + sqlx::IntoArguments<'q, DB>,
+    (i32,): Send + Unpin + for<'r> sqlx::FromRow<'r, DB::Row>,
+{
+    let (count, traces, root) = traced("stream", system, observability, provider, async {
+        let mut rows = sqlx::query_scalar::<_, i32>("select id from affected").fetch(executor);
+        let mut count = 0;
+        while rows.try_next().await.unwrap().is_some() {
+            count += 1;
+            // the span must stay open while the caller consumes the rows
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        count
+    })
+    .await;
+
+    assert_eq!(count, 2);
+    let next = traces.find_child(&root, "SELECT affected").unwrap();
+    assert_eq!(
+        next.int_attribute("db.response.returned_rows").unwrap(),
+        "2"
+    );
+    let idle: u64 = next.int_attribute("idle_ns").unwrap().parse().unwrap();
+    assert!(
+        idle >= 200_000_000,
+        "span closed before the end of the stream"
+    );
+}
+
+pub async fn should_record_error<'c, DB, E>(
+    name: &'static str,
+    system: &'static str,
+    sql: &'static str,
+    expected: &[(&str, &str)],
+    observability: &opentelemetry_testing::ObservabilityContainer,
+    provider: &opentelemetry_testing::OpenTelemetryProvider,
+    executor: E,
+) where
+    DB: sqlx::Database,
+    E: sqlx::Executor<'c, Database = DB>,
+    for<'q> DB::Arguments<'q>: 'q + sqlx::IntoArguments<'q, DB>,
+{
+    let (result, traces, root) = traced(
+        name,
+        system,
+        observability,
+        provider,
+        sqlx::query(sql).execute(executor),
+    )
+    .await;
+
+    assert!(result.is_err());
+    let next = find_query(&traces, &root, sql).unwrap();
+    for (key, value) in expected {
+        let found = next
+            .string_attribute(key)
+            .or_else(|| next.int_attribute(key));
+        assert_eq!(found, Some(*value), "unexpected {key}");
+    }
+}
+
+pub async fn should_trace_acquire<F, T>(
+    system: &'static str,
+    observability: &opentelemetry_testing::ObservabilityContainer,
+    provider: &opentelemetry_testing::OpenTelemetryProvider,
+    acquire: F,
+) where
+    F: Future<Output = Result<T, sqlx::Error>>,
+{
+    let (result, traces, root) = traced("acquire", system, observability, provider, acquire).await;
+
+    assert!(result.is_ok());
+    let next = traces.find_child(&root, "sqlx.acquire").unwrap();
+    assert_eq!(
+        next.string_attribute("db.client.connection.outcome")
+            .unwrap(),
+        "acquired"
+    );
+    assert!(
+        next.int_attribute("db.client.connection.pool.size")
+            .is_some()
+    );
+    assert!(
+        next.int_attribute("db.client.connection.pool.idle")
+            .is_some()
+    );
+}
+
+/// Checks the spans of a transaction running `select 1` before being committed.
+pub async fn should_trace_transaction<F>(
+    system: &'static str,
+    observability: &opentelemetry_testing::ObservabilityContainer,
+    provider: &opentelemetry_testing::OpenTelemetryProvider,
+    transaction: F,
+) where
+    F: Future<Output = Result<(), sqlx::Error>>,
+{
+    let (result, traces, root) =
+        traced("transaction", system, observability, provider, transaction).await;
+
+    result.unwrap();
+    let next = traces.find_child(&root, "db.transaction").unwrap();
+    assert_eq!(next.string_attribute("db.system.name").unwrap(), system);
+    assert_eq!(
+        next.string_attribute("db.transaction.outcome").unwrap(),
+        "committed"
+    );
+    assert_eq!(next.int_attribute("db.transaction.depth").unwrap(), "1");
+    assert_eq!(
+        next.int_attribute("db.transaction.statements").unwrap(),
+        "1"
+    );
+    let queries: Vec<_> = children(&traces, &next.span_id)
+        .filter_map(|span| span.string_attribute("db.query.text"))
+        .collect();
+    for sql in ["BEGIN", "select 1", "COMMIT"] {
+        assert!(queries.contains(&sql), "missing {sql} in {queries:?}");
+    }
+}
@@ -0,0 +1,168 @@
+//! In-process OpenTelemetry exporter, to inspect the spans of a test without a collector.
+//!
+//! The subscriber is installed globally: a span ending on the SQLite worker thread closes its
+//! parent through the subscriber of that thread, so a thread local one would leak the parents.
+//! Each test only sees the spans created on its own thread. Spans below `INFO` are filtered
+//! out and the level of the exported ones is recorded in `level`.
+
+use std::sync::OnceLock;
+
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+use tracing_subscriber::layer::SubscriberExt;
+
+pub struct Memory {
+    exporter: InMemorySpanExporter,
+    provider: SdkTracerProvider,
+    thread: String,
+}
+
+/// Exports the spans created on the current thread.
+pub fn install() -> Memory {
+    static GLOBAL: OnceLock<(InMemorySpanExporter, SdkTracerProvider)> = OnceLock::new();
+
+    let (exporter, provider) = GLOBAL.get_or_init(|| {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let layer = tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer("memory"))
+            .with_level(true);
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_subscriber::filter::LevelFilter::INFO)
+            .with(layer);
+        tracing::subscriber::set_global_default(subscriber).unwrap();
+        (exporter, provider)
+    });
+    Memory {
+        exporter: exporter.clone(),
+        provider: provider.clone(),
+        thread: std::thread::current().name().unwrap().to_string(),
+    }
+}
+
+impl Memory {
+    /// Spans of the current test finished so far, in the order they ended.
+    pub fn finished(&self) -> Vec<SpanData> {
+        self.provider.force_flush().unwrap();
+        self.exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .filter(|span| attribute(span, "thread.name").as_ref() == Some(&self.thread))
+            .collect()
+    }
+
+    /// Finished spans of the current test with the given name, in the order they ended, once
+    /// there are `count` of them.
+    ///
+    /// The SQLite worker holds the span of each command it runs for a little while after
+    /// replying, which can delay the end of the spans.
+    pub async fn spans(&self, name: &str, count: usize) -> Vec<SpanData> {
+        for _ in 0..100 {
+            let spans: Vec<_> = self
+                .finished()
+                .into_iter()
+                .filter(|span| span.name == name)
+                .collect();
+            if spans.len() >= count {
+                return spans;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("expected {count} spans named {name}");
+    }
+}
+
+/// Value of the given attribute of a span, rendered as a string.
+pub fn attribute(span: &SpanData, key: &str) -> Option<String> {
+    span.attributes
+        .iter()
+        .find(|kv| kv.key.as_str() == key)
+        .map(|kv| kv.value.to_string())
+}
+
+/// Value of the given attribute of the first event of a span with the given name.
+pub fn event_attribute(span: &SpanData, name: &str, key: &str) -> Option<String> {
+    span.events
+        .iter()
+        .find(|event| event.name == name)?
+        .attributes
+        .iter()
+        .find(|kv| kv.key.as_str() == key)
+        .map(|kv| kv.value.to_string())
+}
+
+/// Sorted attributes of the points of a histogram, along with the number of recorded values.
+#[cfg(feature = "metrics")]
+pub fn histogram(
+    exporter: &opentelemetry_sdk::metrics::InMemoryMetricExporter,
+    name: &str,
+) -> Vec<(Vec<(String, String)>, u64)> {
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
+
+    let mut points = metric(exporter, name, |data| {
+        let AggregatedMetrics::F64(MetricData::Histogram(histogram)) = data else {
+            panic!("unexpected data for {name}");
+        };
+        histogram
+            .data_points()
+            .map(|point| (labels(point.attributes()), point.count()))
+            .collect::<Vec<_>>()
+    });
+    points.sort();
+    points
+}
+
+/// Sorted attributes of the points of an integer sum, along with their value.
+#[cfg(feature = "metrics")]
+pub fn sum(
+    exporter: &opentelemetry_sdk::metrics::InMemoryMetricExporter,
+    name: &str,
+) -> Vec<(Vec<(String, String)>, i64)> {
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
+
+    let mut points = metric(exporter, name, |data| {
+        let AggregatedMetrics::I64(MetricData::Sum(sum)) = data else {
+            panic!("unexpected data for {name}");
+        };
+        sum.data_points()
+            .map(|point| (labels(point.attributes()), point.value()))
+            .collect::<Vec<_>>()
+    });
+    points.sort();
+    points
+}
+
+/// Reads the data of the last export of the metric with the given name.
+#[cfg(feature = "metrics")]
+fn metric<T>(
+    exporter: &opentelemetry_sdk::metrics::InMemoryMetricExporter,
+    name: &str,
+    read: impl FnOnce(&opentelemetry_sdk::metrics::data::AggregatedMetrics) -> T,
+) -> T {
+    let metrics = exporter.get_finished_metrics().unwrap();
+    let metric = metrics
+        .iter()
+        .rev()
+        .flat_map(|resource| resource.scope_metrics())
+        .flat_map(|scope| scope.metrics())
+        .find(|metric| metric.name() == name)
+        .unwrap_or_else(|| panic!("missing metric {name}"));
+    read(metric.data())
+}
+
+/// Sorted attributes of a metric point, without the address of in-memory databases, which
+/// depends on the other tests.
+#[cfg(feature = "metrics")]
+fn labels<'a>(
+    attributes: impl Iterator<Item = &'a opentelemetry::KeyValue>,
+) -> Vec<(String, String)> {
+    let mut labels: Vec<_> = attributes
+        .filter(|kv| kv.key.as_str() != "server.address")
+        .map(|kv| (kv.key.to_string(), kv.value.to_string()))
+        .collect();
+    labels.sort();
+    labels
+}
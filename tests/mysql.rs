@@ -52,11 +52,27 @@ async fn execute() {
     let container = MySqlContainer::create().await;
     let pool = container.client().await;
 
-    common::should_trace("trace_pool", "mysql", &observability, &provider, &pool).await;
+    common::should_trace(
+        "trace_pool",
+        "mysql",
+        "SELECT test",
+        &observability,
+        &provider,
+        &pool,
+    )
+    .await;
 
     {
         let mut conn = pool.acquire().await.unwrap();
-        common::should_trace("trace_conn", "mysql", &observability, &provider, &mut conn).await;
+        common::should_trace(
+            "trace_conn",
+            "mysql",
+            "SELECT test",
+            &observability,
+            &provider,
+            &mut conn,
+        )
+        .await;
     }
 
     {
@@ -64,12 +80,47 @@ async fn execute() {
         common::should_trace(
             "trace_tx",
             "mysql",
+            "SELECT test",
             &observability,
             &provider,
             &mut tx.executor(),
         )
         .await;
+        common::should_trace(
+            "trace_tx_deref",
+            "mysql",
+            "SELECT test",
+            &observability,
+            &provider,
+            &mut *tx,
+        )
+        .await;
     }
+
+    common::should_record_affected_rows("mysql", &observability, &provider, &pool).await;
+    common::should_trace_stream("mysql", &observability, &provider, &pool).await;
+    common::should_trace_acquire("mysql", &observability, &provider, pool.acquire()).await;
+    common::should_trace_transaction("mysql", &observability, &provider, async {
+        let mut tx = pool.begin().await?;
+        sqlx::query("select 1").execute(&mut *tx).await?;
+        tx.commit().await
+    })
+    .await;
+    common::should_record_error(
+        "missing_table",
+        "mysql",
+        "select * from missing",
+        &[
+            ("db.response.status_code", "1146"),
+            ("error.type", "1146"),
+            ("db.mysql.error.number", "1146"),
+            ("db.mysql.error.sql_state", "42S02"),
+        ],
+        &observability,
+        &provider,
+        &pool,
+    )
+    .await;
 }
 
 #[test]
@@ -77,3 +128,11 @@ fn pool_mysql_is_clone() {
     fn assert_clone<T: Clone>() {}
     assert_clone::<Pool<MySql>>();
 }
+
+#[test]
+fn mysql_types_implement_acquire() {
+    fn assert_acquire<'c, A: sqlx::Acquire<'c, Database = MySql>>() {}
+    assert_acquire::<&Pool<MySql>>();
+    assert_acquire::<&mut sqlx_tracing::PoolConnection<MySql>>();
+    assert_acquire::<&mut sqlx_tracing::Transaction<'_, MySql>>();
+}
@@ -53,13 +53,22 @@ async fn execute() {
     let container = PostgresContainer::create().await;
     let pool = container.client().await;
 
-    common::should_trace("trace_pool", "postgresql", &observability, &provider, &pool).await;
+    common::should_trace(
+        "trace_pool",
+        "postgresql",
+        "SELECT postgres",
+        &observability,
+        &provider,
+        &pool,
+    )
+    .await;
 
     {
         let mut conn = pool.acquire().await.unwrap();
         common::should_trace(
             "trace_conn",
             "postgresql",
+            "SELECT postgres",
             &observability,
             &provider,
             &mut conn,
@@ -72,12 +81,71 @@ async fn execute() {
         common::should_trace(
             "trace_tx",
             "postgresql",
+            "SELECT postgres",
             &observability,
             &provider,
             &mut tx.executor(),
         )
         .await;
+        common::should_trace(
+            "trace_tx_deref",
+            "postgresql",
+            "SELECT postgres",
+            &observability,
+            &provider,
+            &mut *tx,
+        )
+        .await;
     }
+
+    common::should_record_affected_rows("postgresql", &observability, &provider, &pool).await;
+    common::should_trace_stream("postgresql", &observability, &provider, &pool).await;
+    common::should_trace_acquire("postgresql", &observability, &provider, pool.acquire()).await;
+    common::should_trace_transaction("postgresql", &observability, &provider, async {
+        let mut tx = pool.begin().await?;
+        sqlx::query("select 1").execute(&mut *tx).await?;
+        tx.commit().await
+    })
+    .await;
+    common::should_record_error(
+        "missing_table",
+        "postgresql",
+        "select * from missing",
+        &[
+            ("db.response.status_code", "42P01"),
+            ("error.type", "42P01"),
+        ],
+        &observability,
+        &provider,
+        &pool,
+    )
+    .await;
+
+    sqlx::query("create table unique_ids (id integer primary key)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("insert into unique_ids (id) values (1)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    common::should_record_error(
+        "unique_violation",
+        "postgresql",
+        "insert into unique_ids (id) values (1)",
+        &[
+            ("db.response.status_code", "23505"),
+            ("error.type", "unique_violation"),
+            ("db.postgresql.error.schema", "public"),
+            ("db.postgresql.error.table", "unique_ids"),
+            ("db.postgresql.error.constraint", "unique_ids_pkey"),
+            ("db.postgresql.error.detail", "Key (id)=(1) already exists."),
+        ],
+        &observability,
+        &provider,
+        &pool,
+    )
+    .await;
 }
 
 #[test]
@@ -85,3 +153,11 @@ fn pool_postgres_is_clone() {
     fn assert_clone<T: Clone>() {}
     assert_clone::<Pool<Postgres>>();
 }
+
+#[test]
+fn postgres_types_implement_acquire() {
+    fn assert_acquire<'c, A: sqlx::Acquire<'c, Database = Postgres>>() {}
+    assert_acquire::<&Pool<Postgres>>();
+    assert_acquire::<&mut sqlx_tracing::PoolConnection<Postgres>>();
+    assert_acquire::<&mut sqlx_tracing::Transaction<'_, Postgres>>();
+}
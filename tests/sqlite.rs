@@ -13,11 +13,27 @@ async fn execute() {
     let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
     let pool = sqlx_tracing::Pool::from(pool);
 
-    common::should_trace("trace_pool", "sqlite", &observability, &provider, &pool).await;
+    common::should_trace(
+        "trace_pool",
+        "sqlite",
+        "SELECT",
+        &observability,
+        &provider,
+        &pool,
+    )
+    .await;
 
     {
         let mut conn = pool.acquire().await.unwrap();
-        common::should_trace("trace_conn", "sqlite", &observability, &provider, &mut conn).await;
+        common::should_trace(
+            "trace_conn",
+            "sqlite",
+            "SELECT",
+            &observability,
+            &provider,
+            &mut conn,
+        )
+        .await;
     }
 
     {
@@ -25,12 +41,42 @@ async fn execute() {
         common::should_trace(
             "trace_tx",
             "sqlite",
+            "SELECT",
             &observability,
             &provider,
             &mut tx.executor(),
         )
         .await;
+        common::should_trace(
+            "trace_tx_deref",
+            "sqlite",
+            "SELECT",
+            &observability,
+            &provider,
+            &mut *tx,
+        )
+        .await;
     }
+
+    common::should_record_affected_rows("sqlite", &observability, &provider, &pool).await;
+    common::should_trace_stream("sqlite", &observability, &provider, &pool).await;
+    common::should_trace_acquire("sqlite", &observability, &provider, pool.acquire()).await;
+    common::should_trace_transaction("sqlite", &observability, &provider, async {
+        let mut tx = pool.begin().await?;
+        sqlx::query("select 1").execute(&mut *tx).await?;
+        tx.commit().await
+    })
+    .await;
+    common::should_record_error(
+        "missing_table",
+        "sqlite",
+        "select * from missing",
+        &[("db.response.status_code", "1"), ("error.type", "1")],
+        &observability,
+        &provider,
+        &pool,
+    )
+    .await;
 }
 
 #[test]
@@ -38,3 +84,53 @@ fn pool_sqlite_is_clone() {
     fn assert_clone<T: Clone>() {}
     assert_clone::<Pool<Sqlite>>();
 }
+
+#[test]
+#[allow(deprecated)]
+fn instrument_macro_is_exported() {
+    use sqlx_tracing::prelude::Database;
+
+    struct Attributes {
+        name: Option<String>,
+        host: Option<String>,
+        port: Option<u16>,
+        database: Option<String>,
+    }
+
+    type DB = Sqlite;
+    let attributes = Attributes {
+        name: Some("reporting".into()),
+        host: None,
+        port: None,
+        database: Some("main".into()),
+    };
+    let _span = sqlx_tracing::instrument!("sqlx.execute", "select 1", attributes);
+}
+
+#[test]
+fn sqlite_types_implement_acquire() {
+    fn assert_acquire<'c, A: sqlx::Acquire<'c, Database = Sqlite>>() {}
+    assert_acquire::<&Pool<Sqlite>>();
+    assert_acquire::<&mut sqlx_tracing::PoolConnection<Sqlite>>();
+    assert_acquire::<&mut sqlx_tracing::Transaction<'_, Sqlite>>();
+
+    fn assert_traced<'c, A: sqlx_tracing::Acquire<'c, Database = Sqlite>>() {}
+    assert_traced::<&Pool<Sqlite>>();
+    assert_traced::<&mut sqlx_tracing::PoolConnection<Sqlite>>();
+    assert_traced::<&mut sqlx_tracing::Transaction<'_, Sqlite>>();
+}
+
+#[tokio::test]
+async fn transaction_sqlite_is_owned() {
+    let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+    let pool = sqlx_tracing::Pool::from(pool);
+
+    let mut tx: sqlx_tracing::Transaction<'static, Sqlite> = pool.begin().await.unwrap();
+    tokio::spawn(async move {
+        sqlx::query("select 1").execute(&mut tx).await.unwrap();
+        sqlx::query("select 2").execute(&mut tx).await.unwrap();
+        tx.commit().await.unwrap();
+    })
+    .await
+    .unwrap();
+}
@@ -0,0 +1,688 @@
+#![cfg(feature = "sqlite")]
+//! SQLite tests inspecting the spans in process, in their own binary as they install a global
+//! subscriber.
+
+use std::time::Duration;
+
+use sqlx::Sqlite;
+
+mod memory;
+
+#[tokio::test]
+async fn slow_query_sqlite() {
+    let memory = memory::install();
+    let pool =
+        sqlx_tracing::PoolBuilder::from(sqlx::SqlitePool::connect(":memory:").await.unwrap())
+            .with_slow_query_threshold(Duration::ZERO)
+            .build();
+    sqlx::query("select 'secret'").execute(&pool).await.unwrap();
+
+    let spans = memory.spans("SELECT", 1).await;
+    let span = &spans[0];
+    assert_eq!(
+        memory::attribute(span, "db.slow_query").as_deref(),
+        Some("true")
+    );
+    assert_eq!(
+        memory::event_attribute(span, "slow query", "db.query.text").as_deref(),
+        Some("select ?")
+    );
+    let elapsed: f64 = memory::event_attribute(span, "slow query", "elapsed_ms")
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(elapsed > 0.0);
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn operation_duration_sqlite() {
+    use opentelemetry::metrics::MeterProvider;
+
+    let exporter = opentelemetry_sdk::metrics::InMemoryMetricExporter::default();
+    let provider = opentelemetry_sdk::metrics::SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter.clone())
+        .build();
+    let _memory = memory::install();
+    let pool =
+        sqlx_tracing::PoolBuilder::from(sqlx::SqlitePool::connect(":memory:").await.unwrap())
+            .with_meter(&provider.meter("sqlx-tracing"))
+            .build();
+    sqlx::query("create table users (id integer)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("select id from users")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    provider.force_flush().unwrap();
+
+    let points: Vec<_> = memory::histogram(&exporter, "db.client.operation.duration")
+        .into_iter()
+        .map(|(labels, _)| labels)
+        .collect();
+    let expected = |operation: &str| {
+        vec![
+            ("db.collection.name".to_string(), "users".to_string()),
+            ("db.operation.name".to_string(), operation.to_string()),
+            ("db.system.name".to_string(), "sqlite".to_string()),
+        ]
+    };
+    assert_eq!(points, vec![expected("CREATE"), expected("SELECT")]);
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn pool_metrics_sqlite() {
+    use opentelemetry::metrics::MeterProvider;
+
+    let exporter = opentelemetry_sdk::metrics::InMemoryMetricExporter::default();
+    let provider = opentelemetry_sdk::metrics::SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter.clone())
+        .build();
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(3)
+        .connect(":memory:")
+        .await
+        .unwrap();
+    let pool = sqlx_tracing::PoolBuilder::from(pool)
+        .with_name("reporting")
+        .with_meter(&provider.meter("sqlx-tracing"))
+        .build();
+    // the connections go back to the pool in the background
+    let released = async || {
+        while pool.num_idle() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    sqlx::query("select 1").execute(&pool).await.unwrap();
+    released().await;
+    sqlx::query("select 1").fetch_optional(&pool).await.unwrap();
+    released().await;
+    let _used = pool.acquire().await.unwrap();
+    drop(pool.acquire().await.unwrap());
+    released().await;
+    provider.force_flush().unwrap();
+
+    let name = || {
+        (
+            "db.client.connection.pool.name".to_string(),
+            "reporting".to_string(),
+        )
+    };
+    let state = |state: &str| {
+        vec![
+            name(),
+            ("db.client.connection.state".to_string(), state.to_string()),
+        ]
+    };
+    assert_eq!(
+        memory::sum(&exporter, "db.client.connection.count"),
+        vec![(state("idle"), 1), (state("used"), 1)]
+    );
+    assert_eq!(
+        memory::sum(&exporter, "db.client.connection.max"),
+        vec![(vec![name()], 3)]
+    );
+    assert_eq!(
+        memory::sum(&exporter, "db.client.connection.pending_requests"),
+        vec![(vec![name()], 0)]
+    );
+    assert_eq!(
+        memory::histogram(&exporter, "db.client.connection.wait_time"),
+        vec![(vec![name()], 4)]
+    );
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn operation_duration_sqlite_standalone() {
+    use sqlx::Connection;
+
+    let exporter = opentelemetry_sdk::metrics::InMemoryMetricExporter::default();
+    let provider = opentelemetry_sdk::metrics::SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter.clone())
+        .build();
+    opentelemetry::global::set_meter_provider(provider.clone());
+
+    let mut conn = sqlx_tracing::TracedConnection::<Sqlite>::connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::query("create table standalone (id integer)")
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    let mut conn = sqlx::SqliteConnection::connect(":memory:").await.unwrap();
+    sqlx::query("create table wrapped (id integer)")
+        .execute(sqlx_tracing::Traced::new(&mut conn))
+        .await
+        .unwrap();
+    provider.force_flush().unwrap();
+
+    // other tests may record operations with the global provider
+    let points: Vec<_> = memory::histogram(&exporter, "db.client.operation.duration")
+        .into_iter()
+        .filter(|(labels, _)| {
+            labels.iter().any(|(key, value)| {
+                key == "db.collection.name" && ["standalone", "wrapped"].contains(&value.as_str())
+            })
+        })
+        .collect();
+    let expected = |collection: &str| {
+        (
+            vec![
+                ("db.collection.name".to_string(), collection.to_string()),
+                ("db.operation.name".to_string(), "CREATE".to_string()),
+                ("db.system.name".to_string(), "sqlite".to_string()),
+            ],
+            1,
+        )
+    };
+    assert_eq!(points, vec![expected("standalone"), expected("wrapped")]);
+}
+
+#[tokio::test]
+async fn transaction_sqlite_dropped() {
+    let memory = memory::install();
+    let pool = sqlx::pool::PoolOptions::<Sqlite>::new()
+        .max_connections(1)
+        .connect(":memory:")
+        .await
+        .unwrap();
+    let pool = sqlx_tracing::Pool::from(pool);
+
+    {
+        let _conn = pool.acquire().await.unwrap();
+        let begin = tokio::time::timeout(Duration::from_millis(10), pool.begin()).await;
+        assert!(begin.is_err());
+    }
+    let (begin, line) = (pool.begin(), line!());
+    drop(begin.await.unwrap());
+
+    let spans = memory.spans("db.transaction", 2).await;
+    let [cancelled, dropped] = spans.as_slice() else {
+        panic!("unexpected transaction spans: {spans:?}");
+    };
+    let message = "transaction dropped without commit or rollback";
+    assert_eq!(
+        memory::attribute(cancelled, "db.transaction.outcome").as_deref(),
+        Some("cancelled")
+    );
+    assert_eq!(
+        memory::event_attribute(cancelled, message, "code.line.number"),
+        None
+    );
+    assert_eq!(
+        memory::attribute(dropped, "db.transaction.outcome").as_deref(),
+        Some("implicit_rollback")
+    );
+    assert_eq!(
+        memory::event_attribute(dropped, message, "code.line.number"),
+        Some(line.to_string())
+    );
+    assert_eq!(
+        memory::event_attribute(dropped, message, "code.file.path"),
+        Some(file!().to_string())
+    );
+}
+
+#[tokio::test]
+async fn transaction_sqlite_savepoint() {
+    let memory = memory::install();
+    let pool = sqlx_tracing::Pool::from(sqlx::SqlitePool::connect(":memory:").await.unwrap());
+
+    let mut tx = pool.begin().await.unwrap();
+    let savepoint = tx.begin().await.unwrap();
+    savepoint.rollback().await.unwrap();
+    tx.commit().await.unwrap();
+
+    let transaction = &memory.spans("db.transaction", 1).await[0];
+    let savepoint = &memory.spans("db.savepoint", 1).await[0];
+    assert_eq!(savepoint.parent_span_id, transaction.span_context.span_id());
+    assert_eq!(
+        memory::attribute(transaction, "db.transaction.depth").as_deref(),
+        Some("1")
+    );
+    assert_eq!(
+        memory::attribute(transaction, "db.transaction.outcome").as_deref(),
+        Some("committed")
+    );
+    assert_eq!(
+        memory::attribute(savepoint, "db.transaction.depth").as_deref(),
+        Some("2")
+    );
+    assert_eq!(
+        memory::attribute(savepoint, "db.transaction.outcome").as_deref(),
+        Some("rolled_back")
+    );
+    for (name, sql) in [
+        ("SAVEPOINT", "SAVEPOINT _sqlx_savepoint_1"),
+        ("ROLLBACK", "ROLLBACK TO SAVEPOINT _sqlx_savepoint_1"),
+    ] {
+        let span = &memory.spans(name, 1).await[0];
+        assert_eq!(span.parent_span_id, savepoint.span_context.span_id());
+        assert_eq!(
+            memory::attribute(span, "db.query.text").as_deref(),
+            Some(sql)
+        );
+    }
+}
+
+async fn insert_user<'c, A>(db: A) -> sqlx::Result<()>
+where
+    A: sqlx_tracing::Acquire<'c, Database = Sqlite>,
+{
+    let mut conn = db.acquire().await?;
+    sqlx::query("insert into users default values")
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn acquire_sqlite_traced() {
+    use sqlx_tracing::Acquire;
+
+    let memory = memory::install();
+    let pool = sqlx::pool::PoolOptions::<Sqlite>::new()
+        .max_connections(1)
+        .connect(":memory:")
+        .await
+        .unwrap();
+    let pool = sqlx_tracing::Pool::from(pool);
+    sqlx::query("create table users (id integer primary key)")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    insert_user(&pool).await.unwrap();
+    {
+        let mut conn = pool.acquire().await.unwrap();
+        insert_user(&mut conn).await.unwrap();
+    }
+    let mut tx = pool.begin().await.unwrap();
+    insert_user(&mut tx).await.unwrap();
+    let mut savepoint = Acquire::begin(&mut tx).await.unwrap();
+    insert_user(&mut savepoint).await.unwrap();
+    savepoint.commit().await.unwrap();
+    tx.commit().await.unwrap();
+
+    let inserts = memory.spans("INSERT users", 4).await;
+    let transaction = &memory.spans("db.transaction", 1).await[0];
+    let savepoint = &memory.spans("db.savepoint", 1).await[0];
+    assert_eq!(
+        inserts[2].parent_span_id,
+        transaction.span_context.span_id()
+    );
+    assert_eq!(inserts[3].parent_span_id, savepoint.span_context.span_id());
+    assert_eq!(
+        memory::attribute(transaction, "db.transaction.statements").as_deref(),
+        Some("1")
+    );
+}
+
+#[tokio::test]
+async fn try_begin_sqlite_traced() {
+    let memory = memory::install();
+    let pool = sqlx::pool::PoolOptions::<Sqlite>::new()
+        .max_connections(1)
+        .connect(":memory:")
+        .await
+        .unwrap();
+    let pool = sqlx_tracing::Pool::from(pool);
+
+    let tx = pool.try_begin().await.unwrap().unwrap();
+    assert!(pool.try_begin().await.unwrap().is_none());
+    tx.commit().await.unwrap();
+
+    // the acquisitions end in order, the second transaction ends first, without any connection
+    let acquires = memory.spans("sqlx.acquire", 2).await;
+    let transactions = memory.spans("db.transaction", 2).await;
+    let id = |span: &opentelemetry_sdk::trace::SpanData| span.span_context.span_id();
+    assert_eq!(acquires[0].parent_span_id, id(&transactions[1]));
+    assert_eq!(acquires[1].parent_span_id, id(&transactions[0]));
+    assert_eq!(
+        memory::attribute(&acquires[1], "db.client.connection.outcome").as_deref(),
+        Some("unavailable")
+    );
+    let outcome = |span| memory::attribute(span, "db.transaction.outcome");
+    assert_eq!(outcome(&transactions[0]).as_deref(), Some("unavailable"));
+    assert_eq!(outcome(&transactions[1]).as_deref(), Some("committed"));
+}
+
+#[tokio::test]
+async fn pool_sqlite_close() {
+    let memory = memory::install();
+    let pool =
+        sqlx_tracing::PoolBuilder::from(sqlx::SqlitePool::connect(":memory:").await.unwrap())
+            .with_name("closing")
+            .build();
+
+    assert!(pool.try_acquire().is_some());
+    assert!(pool.size() >= 1);
+    pool.close().await;
+    assert!(pool.is_closed());
+    assert!(pool.as_inner().is_closed());
+    assert!(pool.try_acquire().is_none());
+
+    let span = &memory.spans("sqlx.close", 1).await[0];
+    assert_eq!(
+        memory::attribute(span, "db.client.connection.pool.name").as_deref(),
+        Some("closing")
+    );
+    let drain_time = memory::attribute(span, "db.client.connection.drain_time").unwrap();
+    assert!(drain_time.parse::<f64>().unwrap() >= 0.0);
+}
+
+#[tokio::test]
+async fn pool_sqlite_connect() {
+    let memory = memory::install();
+    let pool = sqlx_tracing::PoolBuilder::<Sqlite>::connect("sqlite::memory:")
+        .await
+        .unwrap()
+        .with_name("connected")
+        .build();
+    sqlx::query("select 1").execute(&pool).await.unwrap();
+
+    let pool = sqlx_tracing::PoolBuilder::<Sqlite>::connect_lazy("sqlite::memory:")
+        .unwrap()
+        .build();
+    assert_eq!(pool.size(), 0);
+    sqlx::query("select 1").execute(&pool).await.unwrap();
+
+    let options = "sqlite:/nonexistent/dir/db.sqlite".parse().unwrap();
+    let pool_options = sqlx::pool::PoolOptions::new();
+    let result = sqlx_tracing::PoolBuilder::<Sqlite>::connect_with(pool_options, options).await;
+    assert!(result.is_err());
+
+    // the lazy pool connects on its first query, without any span
+    let spans = memory.spans("sqlx.connect", 2).await;
+    assert_eq!(spans.len(), 2);
+    for span in &spans {
+        assert_eq!(
+            memory::attribute(span, "db.system.name").as_deref(),
+            Some("sqlite")
+        );
+    }
+    let (connected, failed) = (&spans[0], &spans[1]);
+    assert!(memory::attribute(connected, "net.peer.name").is_some());
+    assert_eq!(memory::attribute(connected, "error.type"), None);
+    assert_eq!(connected.status, opentelemetry::trace::Status::Unset);
+    assert_eq!(
+        memory::attribute(failed, "net.peer.name").as_deref(),
+        Some("/nonexistent/dir/db.sqlite")
+    );
+    assert!(memory::attribute(failed, "error.type").is_some());
+    assert!(matches!(
+        failed.status,
+        opentelemetry::trace::Status::Error { .. }
+    ));
+}
+
+#[tokio::test]
+async fn traced_connection_sqlite() {
+    let memory = memory::install();
+    let mut conn = sqlx_tracing::TracedConnection::<Sqlite>::connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::query("select 1").execute(&mut conn).await.unwrap();
+    conn.ping().await.unwrap();
+    let mut tx = conn.begin().await.unwrap();
+    sqlx::query("select 2").execute(&mut tx).await.unwrap();
+    tx.commit().await.unwrap();
+    conn.close().await.unwrap();
+
+    let transaction = &memory.spans("db.transaction", 1).await[0];
+    assert_eq!(
+        memory::attribute(transaction, "db.transaction.outcome").as_deref(),
+        Some("committed")
+    );
+    for name in ["sqlx.connect", "sqlx.ping", "sqlx.close"] {
+        let span = &memory.spans(name, 1).await[0];
+        assert_eq!(
+            memory::attribute(span, "db.system.name").as_deref(),
+            Some("sqlite"),
+            "{name}"
+        );
+        // legacy attributes by default, the host being the file of the database
+        assert!(memory::attribute(span, "net.peer.name").is_some(), "{name}");
+        assert_eq!(memory::attribute(span, "server.address"), None, "{name}");
+        assert_eq!(memory::attribute(span, "error.type"), None, "{name}");
+    }
+}
+
+#[tokio::test]
+async fn traced_sqlite_executor() {
+    use sqlx::Connection;
+    use sqlx_tracing::{SemconvStability, SpanNaming};
+
+    let memory = memory::install();
+    let pool =
+        sqlx_tracing::PoolBuilder::from(sqlx::SqlitePool::connect(":memory:").await.unwrap())
+            .with_name("reporting")
+            .with_database("main")
+            .with_semconv_stability(SemconvStability::Legacy)
+            .build();
+    let mut conn = sqlx::SqliteConnection::connect(":memory:").await.unwrap();
+    sqlx::query("select 1")
+        .execute(pool.traced(&mut conn))
+        .await
+        .unwrap();
+
+    let mut tx = conn.begin().await.unwrap();
+    let mut traced = sqlx_tracing::Traced::new(&mut *tx)
+        .with_database("main")
+        .with_span_naming(SpanNaming::Method)
+        .with_semconv_stability(SemconvStability::Stable)
+        .with_query_sanitization(true)
+        .with_slow_query_threshold(Duration::ZERO);
+    sqlx::query("select 2").execute(&mut traced).await.unwrap();
+    sqlx::query("select 3").execute(&mut traced).await.unwrap();
+    tx.commit().await.unwrap();
+
+    // the attributes of the pool are shared with the executor
+    let span = &memory.spans("SELECT main", 1).await[0];
+    for (key, value) in [
+        ("db.name", Some("main")),
+        ("peer.service", Some("reporting")),
+    ] {
+        assert_eq!(memory::attribute(span, key).as_deref(), value, "{key}");
+    }
+    let spans = memory.spans("sqlx.execute", 2).await;
+    assert_eq!(spans.len(), 2);
+    for span in &spans {
+        for (key, value) in [
+            ("db.namespace", Some("main")),
+            ("db.name", None),
+            ("db.query.text", Some("select ?")),
+            ("db.slow_query", Some("true")),
+        ] {
+            assert_eq!(memory::attribute(span, key).as_deref(), value, "{key}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn traced_sqlite_attributes() {
+    use sqlx::Connection;
+
+    let memory = memory::install();
+    let mut conn = sqlx::SqliteConnection::connect(":memory:").await.unwrap();
+    let mut traced = sqlx_tracing::Traced::new(&mut conn)
+        .with_name("reporting")
+        .with_database("main")
+        .with_host("localhost")
+        .with_port(1234)
+        .with_level(tracing::Level::WARN);
+    sqlx::query("select 1").execute(&mut traced).await.unwrap();
+
+    let span = &memory.spans("SELECT main", 1).await[0];
+    for (key, value) in [
+        ("level", "WARN"),
+        ("db.name", "main"),
+        ("net.peer.name", "localhost"),
+        ("net.peer.port", "1234"),
+    ] {
+        assert_eq!(memory::attribute(span, key).as_deref(), Some(value));
+    }
+}
+
+#[tokio::test]
+async fn semconv_sqlite_attributes() {
+    use sqlx_tracing::SemconvStability;
+
+    let memory = memory::install();
+    let pool = sqlx::pool::PoolOptions::<Sqlite>::new()
+        .max_connections(1)
+        .connect(":memory:")
+        .await
+        .unwrap();
+    sqlx::query("create table users (id integer)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    for stability in [
+        SemconvStability::Legacy,
+        SemconvStability::Stable,
+        SemconvStability::Both,
+    ] {
+        let pool = sqlx_tracing::PoolBuilder::from(pool.clone())
+            .with_database("main")
+            .with_host("localhost")
+            .with_port(1234)
+            .with_semconv_stability(stability)
+            .build();
+        sqlx::query("select id from users")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    }
+
+    let spans = memory.spans("SELECT users", 3).await;
+    let common = [
+        ("db.system.name", "sqlite"),
+        ("db.query.text", "select id from users"),
+    ];
+    let stable = [
+        ("db.namespace", "main"),
+        ("db.operation.name", "SELECT"),
+        ("db.collection.name", "users"),
+        ("server.address", "localhost"),
+        ("server.port", "1234"),
+    ];
+    let legacy = [
+        ("db.name", "main"),
+        ("db.operation", "SELECT"),
+        ("db.sql.table", "users"),
+        ("net.peer.name", "localhost"),
+        ("net.peer.port", "1234"),
+    ];
+    let expected = [(false, true), (true, false), (true, true)];
+    for (span, (has_stable, has_legacy)) in spans.iter().zip(expected) {
+        for (key, value) in common {
+            assert_eq!(memory::attribute(span, key).as_deref(), Some(value));
+        }
+        for (key, value) in stable {
+            let expected = has_stable.then_some(value);
+            assert_eq!(memory::attribute(span, key).as_deref(), expected, "{key}");
+        }
+        for (key, value) in legacy {
+            let expected = has_legacy.then_some(value);
+            assert_eq!(memory::attribute(span, key).as_deref(), expected, "{key}");
+        }
+        assert_eq!(memory::attribute(span, "db.statement"), None);
+        assert_eq!(memory::attribute(span, "db.system"), None);
+    }
+}
+
+#[tokio::test]
+async fn level_sqlite_filtered() {
+    let memory = memory::install();
+    let pool = sqlx::pool::PoolOptions::<Sqlite>::new()
+        .max_connections(1)
+        .connect(":memory:")
+        .await
+        .unwrap();
+    let pool = sqlx_tracing::PoolBuilder::from(pool)
+        .with_level(tracing::Level::DEBUG)
+        .with_lifecycle_level(tracing::Level::WARN)
+        .build();
+
+    let mut tx = pool.begin().await.unwrap();
+    sqlx::query("select 1").execute(&mut *tx).await.unwrap();
+    tx.commit().await.unwrap();
+
+    let transaction = &memory.spans("db.transaction", 1).await[0];
+    assert_eq!(
+        memory::attribute(transaction, "level").as_deref(),
+        Some("WARN")
+    );
+    assert_eq!(
+        memory::attribute(transaction, "db.transaction.outcome").as_deref(),
+        Some("committed")
+    );
+    // the query spans are below the enabled level of the subscriber
+    let names: Vec<_> = memory
+        .finished()
+        .into_iter()
+        .map(|span| span.name.into_owned())
+        .collect();
+    for name in ["BEGIN", "SELECT", "COMMIT"] {
+        assert!(!names.iter().any(|n| n == name), "{name} in {names:?}");
+    }
+}
+
+/// Asserts the span carries the database name and location set on the pool, under the names
+/// of the given stability mode.
+fn assert_location(
+    span: &opentelemetry_sdk::trace::SpanData,
+    stability: sqlx_tracing::SemconvStability,
+) {
+    use sqlx_tracing::SemconvStability;
+
+    let has_stable = matches!(stability, SemconvStability::Stable | SemconvStability::Both);
+    let has_legacy = matches!(stability, SemconvStability::Legacy | SemconvStability::Both);
+    for (key, value, present) in [
+        ("db.namespace", "main", has_stable),
+        ("server.address", "localhost", has_stable),
+        ("server.port", "1234", has_stable),
+        ("db.name", "main", has_legacy),
+        ("net.peer.name", "localhost", has_legacy),
+        ("net.peer.port", "1234", has_legacy),
+    ] {
+        let expected = present.then_some(value);
+        assert_eq!(memory::attribute(span, key).as_deref(), expected, "{key}");
+    }
+}
+
+#[tokio::test]
+async fn semconv_sqlite_transaction() {
+    use sqlx_tracing::SemconvStability;
+
+    let memory = memory::install();
+    let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+    let modes = [
+        SemconvStability::Legacy,
+        SemconvStability::Stable,
+        SemconvStability::Both,
+    ];
+    for stability in modes {
+        let pool = sqlx_tracing::PoolBuilder::from(pool.clone())
+            .with_database("main")
+            .with_host("localhost")
+            .with_port(1234)
+            .with_semconv_stability(stability)
+            .build();
+        let mut tx = pool.begin().await.unwrap();
+        tx.begin().await.unwrap().commit().await.unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    let transactions = memory.spans("db.transaction", 3).await;
+    let savepoints = memory.spans("db.savepoint", 3).await;
+    for (index, stability) in modes.into_iter().enumerate() {
+        assert_location(&transactions[index], stability);
+        assert_location(&savepoints[index], stability);
+    }
+}